@@ -0,0 +1,120 @@
+//! Zeroizing registered secrets in the child process after `fork`.
+//!
+//! The registry is guarded by a spinlock instead of a `Mutex`: the `fork` handlers take it
+//! in the parent right before forking and release it in both processes afterwards, which
+//! keeps another thread from forking while the registry is half updated.
+
+use std::{
+    cell::UnsafeCell,
+    ptr::NonNull,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Once,
+    },
+};
+
+use errno::Errno;
+
+use crate::{
+    error::fail,
+    memory::{self, Access},
+};
+
+/// Secret registered with [`register`].
+struct Entry {
+    secret: NonNull<()>,
+    zeroize: unsafe fn(NonNull<()>),
+    /// Pages of a secret that is inaccessible while it isn't exposed.
+    protected: Option<(usize, usize)>,
+}
+
+struct Registry {
+    locked: AtomicBool,
+    entries: UnsafeCell<Vec<Entry>>,
+}
+
+// SAFETY: `entries` is only accessed while `locked` is held.
+unsafe impl Sync for Registry {}
+
+static REGISTRY: Registry = Registry {
+    locked: AtomicBool::new(false),
+    entries: UnsafeCell::new(Vec::new()),
+};
+
+static INSTALL_HANDLERS: Once = Once::new();
+
+impl Registry {
+    fn lock(&self) {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            std::hint::spin_loop();
+        }
+    }
+
+    fn unlock(&self) {
+        self.locked.store(false, Ordering::Release);
+    }
+
+    fn with_entries<R>(&self, f: impl FnOnce(&mut Vec<Entry>) -> R) -> R {
+        self.lock();
+        let result = f(unsafe { &mut *self.entries.get() });
+        self.unlock();
+        result
+    }
+}
+
+extern "C" fn prepare() {
+    REGISTRY.lock();
+}
+
+extern "C" fn parent() {
+    REGISTRY.unlock();
+}
+
+extern "C" fn child() {
+    for entry in unsafe { &*REGISTRY.entries.get() } {
+        // The zeroized secret is left accessible, the child has nothing left to hide
+        if let Some((start, len)) = entry.protected {
+            if unsafe { memory::protect_memory(start, len, Access::ReadWrite) }.is_err() {
+                continue;
+            }
+        }
+        unsafe { (entry.zeroize)(entry.secret) };
+    }
+    REGISTRY.unlock();
+}
+
+/// Zeroize the secret at `secret` with `zeroize` in every child forked from now on.
+pub(crate) fn register(
+    secret: NonNull<()>,
+    zeroize: unsafe fn(NonNull<()>),
+    protected: Option<(usize, usize)>,
+) {
+    INSTALL_HANDLERS.call_once(|| {
+        let result = unsafe { libc::pthread_atfork(Some(prepare), Some(parent), Some(child)) };
+        if result != 0 {
+            fail(format_args!("pthread_atfork failed: {}", Errno(result)));
+        }
+    });
+    REGISTRY.with_entries(|entries| {
+        if !entries.iter().any(|entry| entry.secret == secret) {
+            entries.push(Entry {
+                secret,
+                zeroize,
+                protected,
+            });
+        }
+    });
+}
+
+/// Stop zeroizing the secret at `secret` after `fork`, e.g. because it's dropped.
+pub(crate) fn unregister(secret: NonNull<()>) {
+    // Fast path for processes that never registered a secret
+    if !INSTALL_HANDLERS.is_completed() {
+        return;
+    }
+    REGISTRY.with_entries(|entries| entries.retain(|entry| entry.secret != secret));
+}
@@ -0,0 +1,195 @@
+use zeroize::Zeroize;
+
+use crate::{error::fail, memory::LockMode, SecretBox, SecretBoxError};
+
+/// Configures how a [`SecretBox`] protects its secret, e.g. to skip locking a short-lived
+/// nonce while giving a long-lived master key every protection available.
+///
+/// The defaults match [`SecretBox::new`]: the secret is locked and excluded from core dumps,
+/// but neither inaccessible while idle nor surrounded by guard pages. The policy is kept with
+/// the secret, so dropping it undoes exactly what was applied and clones are protected the
+/// same way.
+///
+/// ```
+/// use shush_rs::{ExposeSecret, SecretBoxBuilder};
+///
+/// let master_key = SecretBoxBuilder::new()
+///     .mprotect_idle(true)
+///     .guard_pages(true)
+///     .build(Box::new([1u8; 32]));
+/// let nonce = SecretBoxBuilder::new().mlock(false).build(Box::new([2u8; 12]));
+/// assert_eq!(master_key.expose_secret()[0], 1);
+/// assert_eq!(nonce.expose_secret()[0], 2);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SecretBoxBuilder {
+    pub(crate) lock: LockMode,
+    pub(crate) dump_protect: bool,
+    pub(crate) mprotect_idle: bool,
+    pub(crate) guard_pages: bool,
+    pub(crate) lock_on_fault: bool,
+    pub(crate) memfd_secret: bool,
+}
+
+impl SecretBoxBuilder {
+    /// Create a builder with the protection of [`SecretBox::new`].
+    pub const fn new() -> Self {
+        Self {
+            lock: LockMode::Required,
+            dump_protect: true,
+            mprotect_idle: false,
+            guard_pages: false,
+            lock_on_fault: false,
+            memfd_secret: false,
+        }
+    }
+
+    /// Whether to lock the secret's pages into memory, see [`SecretBox::new_unlocked`].
+    /// Defaults to `true`.
+    pub const fn mlock(mut self, mlock: bool) -> Self {
+        self.lock = if mlock {
+            LockMode::Required
+        } else {
+            LockMode::Skip
+        };
+        self
+    }
+
+    /// Whether to exclude the secret's pages from core dumps where
+    /// [`dump_protection_available`](crate::dump_protection_available). Defaults to `true`.
+    pub const fn dump_protect(mut self, dump_protect: bool) -> Self {
+        self.dump_protect = dump_protect;
+        self
+    }
+
+    /// Whether to keep the secret's pages inaccessible while it isn't exposed, see
+    /// [`SecretBox::new_protected`]. Defaults to `false`.
+    pub const fn mprotect_idle(mut self, mprotect_idle: bool) -> Self {
+        self.mprotect_idle = mprotect_idle;
+        self
+    }
+
+    /// Whether to surround the secret's pages with inaccessible guard pages, see
+    /// [`SecretBox::new_guarded`]. Defaults to `false`.
+    pub const fn guard_pages(mut self, guard_pages: bool) -> Self {
+        self.guard_pages = guard_pages;
+        self
+    }
+
+    /// Whether to lock pages only once they're first touched, with `mlock2(MLOCK_ONFAULT)`.
+    /// Defaults to `false`.
+    ///
+    /// This saves faulting in every page up front, e.g. for a large, sparsely used buffer,
+    /// while still keeping touched pages from being swapped out. Only Linux 4.4 and later
+    /// support it, elsewhere the pages are locked up front as usual. Until its pages are
+    /// touched, [`SecretBox::is_locked`] reports such a secret as not locked.
+    pub const fn lock_on_fault(mut self, lock_on_fault: bool) -> Self {
+        self.lock_on_fault = lock_on_fault;
+        self
+    }
+
+    /// Whether to map the secret's pages with `memfd_secret(2)`, see
+    /// [`SecretBox::new_memfd_secret`]. Defaults to `false`.
+    #[cfg(all(target_os = "linux", feature = "memfd-secret"))]
+    pub const fn memfd_secret(mut self, memfd_secret: bool) -> Self {
+        self.memfd_secret = memfd_secret;
+        self
+    }
+
+    /// Create a secret value using a pre-boxed value, protected as configured.
+    ///
+    /// With [`Self::mprotect_idle`], [`Self::guard_pages`] or `memfd_secret` the secret is
+    /// moved to pages of its own like with [`SecretBox::new_locked_pages`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret's memory can't be protected. See [`Self::try_build`] for a
+    /// non-panicking alternative.
+    pub fn build<S: Zeroize>(self, boxed_secret: Box<S>) -> SecretBox<S> {
+        self.try_build(boxed_secret).unwrap_or_else(|err| fail(err))
+    }
+
+    /// Same as [`Self::build`], but returns an error if the secret's memory can't be
+    /// protected.
+    ///
+    /// The secret is zeroized before the error is returned.
+    pub fn try_build<S: Zeroize>(
+        self,
+        boxed_secret: Box<S>,
+    ) -> Result<SecretBox<S>, SecretBoxError> {
+        if self.mprotect_idle || self.guard_pages || self.memfd_secret {
+            SecretBox::try_new_on_pages(boxed_secret, self)
+        } else {
+            SecretBox::try_new_with_policy(boxed_secret, self)
+        }
+    }
+}
+
+impl Default for SecretBoxBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backing, ExposeSecret};
+
+    #[test]
+    fn test_builder_defaults() {
+        let secret_box = SecretBoxBuilder::default().build(Box::new([1u8; 32]));
+        assert_eq!(*secret_box.expose_secret(), [1; 32]);
+        assert_eq!(secret_box.region.locked, !cfg!(feature = "no-mlock"));
+        assert_eq!(
+            secret_box.region.dump_excluded,
+            crate::dump_protection_available()
+        );
+        assert!(matches!(secret_box.backing, Backing::Heap));
+        assert!(secret_box.idle_protection.is_none());
+    }
+
+    #[test]
+    fn test_builder_policy() {
+        let secret_box = SecretBoxBuilder::new()
+            .mlock(false)
+            .dump_protect(false)
+            .build(Box::new([1u8; 32]));
+        assert!(!secret_box.region.locked);
+        assert!(!secret_box.region.dump_excluded);
+
+        let cloned = secret_box.clone();
+        assert!(!cloned.region.locked);
+        assert!(!cloned.region.dump_excluded);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_builder_lock_on_fault() {
+        let secret_box = SecretBoxBuilder::new()
+            .lock_on_fault(true)
+            .build(Box::new([1u8; 32]));
+        assert_eq!(secret_box.region.locked, !cfg!(feature = "no-mlock"));
+        assert_eq!(*secret_box.expose_secret(), [1; 32]);
+        assert_eq!(secret_box.is_locked().unwrap(), !cfg!(feature = "no-mlock"));
+    }
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn test_builder_pages() {
+        let policy = SecretBoxBuilder::new()
+            .mprotect_idle(true)
+            .guard_pages(true);
+        let secret_box = policy.build(Box::new([1u8; 32]));
+        assert!(secret_box.idle_protection.is_some());
+        assert!(matches!(
+            secret_box.backing,
+            Backing::Pages { start, .. } if start != secret_box.region.start
+        ));
+        assert_eq!(*secret_box.expose_secret(), [1; 32]);
+
+        let cloned = secret_box.clone();
+        assert_eq!(cloned.policy, policy);
+        assert!(cloned.idle_protection.is_some());
+    }
+}
@@ -0,0 +1,225 @@
+use std::fmt;
+
+#[cfg(any(unix, windows))]
+use errno::Errno;
+
+/// Errors that can occur while protecting the memory of a [`SecretBox`](crate::SecretBox).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SecretBoxError {
+    /// The system page size could not be determined.
+    #[cfg(any(unix, windows))]
+    PageSize(Errno),
+    /// Locking the secret's pages into memory failed.
+    #[cfg(any(unix, windows))]
+    Mlock(Errno),
+    /// Excluding the secret's pages from core dumps (or restoring them) failed.
+    #[cfg(any(unix, windows))]
+    Madvise(Errno),
+    /// Unlocking the secret's pages failed.
+    #[cfg(any(unix, windows))]
+    Munlock(Errno),
+    /// Mapping or unmapping dedicated pages for the secret failed.
+    #[cfg(any(unix, windows))]
+    Mmap(Errno),
+    /// Changing the access protection of the secret's pages failed.
+    #[cfg(any(unix, windows))]
+    Mprotect(Errno),
+    /// Keeping the secret's pages from being inherited by child processes failed.
+    #[cfg(any(target_os = "openbsd", target_os = "freebsd", target_os = "netbsd"))]
+    Minherit(Errno),
+    /// The platform has no way to protect memory, e.g. `wasm32-unknown-unknown`.
+    Unsupported,
+    /// The page-aligned range spanning the secret doesn't fit in the address space or is
+    /// larger than `isize::MAX` bytes.
+    RegionOverflow,
+    /// The system reported a page size that isn't a power of two.
+    InvalidPageSize(usize),
+    /// The secret needs a larger alignment than a page offers, so it can't be placed on pages
+    /// of its own.
+    OverAligned {
+        /// Alignment the secret's type requires.
+        align: usize,
+        /// Size, and thus alignment, of a page.
+        page_size: usize,
+    },
+    /// Locking `requested` more bytes would exceed the `budget` set with
+    /// [`set_memlock_budget`](crate::set_memlock_budget).
+    MemlockBudget {
+        /// Page-aligned length of the lock that was refused.
+        requested: usize,
+        /// Budget in effect when the lock was refused.
+        budget: usize,
+    },
+    /// The operating system couldn't provide random bytes for a random secret.
+    #[cfg(feature = "rand")]
+    Random(getrandom::Error),
+}
+
+impl fmt::Display for SecretBoxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(any(unix, windows))]
+            SecretBoxError::PageSize(errno) => write!(f, "error getting page size: {}", errno),
+            #[cfg(any(unix, windows))]
+            SecretBoxError::Mlock(errno) => write!(f, "mlock failed: {}", errno),
+            #[cfg(any(unix, windows))]
+            SecretBoxError::Madvise(errno) => write!(f, "madvise failed: {}", errno),
+            #[cfg(any(unix, windows))]
+            SecretBoxError::Munlock(errno) => write!(f, "munlock failed: {}", errno),
+            #[cfg(any(unix, windows))]
+            SecretBoxError::Mmap(errno) => write!(f, "mmap failed: {}", errno),
+            #[cfg(any(unix, windows))]
+            SecretBoxError::Mprotect(errno) => write!(f, "mprotect failed: {}", errno),
+            #[cfg(any(target_os = "openbsd", target_os = "freebsd", target_os = "netbsd"))]
+            SecretBoxError::Minherit(errno) => write!(f, "minherit failed: {}", errno),
+            SecretBoxError::Unsupported => {
+                write!(f, "memory protection is not supported on this platform")
+            }
+            SecretBoxError::RegionOverflow => {
+                write!(
+                    f,
+                    "secret's memory range overflows the address space or exceeds isize::MAX bytes"
+                )
+            }
+            SecretBoxError::InvalidPageSize(page_size) => {
+                write!(f, "page size {} is not a power of two", page_size)
+            }
+            SecretBoxError::OverAligned { align, page_size } => write!(
+                f,
+                "secret needs an alignment of {} bytes, more than a page of {} bytes offers",
+                align, page_size
+            ),
+            SecretBoxError::MemlockBudget { requested, budget } => write!(
+                f,
+                "locking {} more bytes would exceed the memlock budget of {} bytes",
+                requested, budget
+            ),
+            #[cfg(feature = "rand")]
+            SecretBoxError::Random(err) => write!(f, "generating random bytes failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SecretBoxError {}
+
+/// Give up after a failure that would leave a secret unprotected.
+///
+/// Panics by default. With the `fail-hard` feature this prints the error and aborts instead,
+/// so a `catch_unwind` up the stack can't let the program carry on with the secret exposed.
+#[cold]
+pub(crate) fn fail(err: impl fmt::Display) -> ! {
+    #[cfg(feature = "fail-hard")]
+    {
+        eprintln!("shush-rs: {}", err);
+        std::process::abort()
+    }
+    #[cfg(not(feature = "fail-hard"))]
+    panic!("{}", err)
+}
+
+/// Error returned by [`SecretBox::drop_verified`](crate::SecretBox::drop_verified) when the
+/// secret wasn't fully zeroized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZeroizeVerifyError {
+    /// Offset of the first byte that was still set, counted from the start of the secret's
+    /// data.
+    pub offset: usize,
+}
+
+impl fmt::Display for ZeroizeVerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "secret not zeroized at offset {}", self.offset)
+    }
+}
+
+impl std::error::Error for ZeroizeVerifyError {}
+
+/// Error returned by [`SecretBox::assert_resident`](crate::SecretBox::assert_resident).
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ResidencyError {
+    /// The page at this address isn't resident in RAM, e.g. because it was swapped out.
+    NotResident(usize),
+    /// The page at this address is resident but not locked, so it could be swapped out.
+    NotLocked(usize),
+    /// `mincore` or reading `/proc/self/smaps` failed.
+    Io(std::io::Error),
+}
+
+#[cfg(target_os = "linux")]
+impl fmt::Display for ResidencyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResidencyError::NotResident(address) => {
+                write!(f, "page at {:#x} is not resident", address)
+            }
+            ResidencyError::NotLocked(address) => write!(f, "page at {:#x} is not locked", address),
+            ResidencyError::Io(err) => write!(f, "failed to query residency: {}", err),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl std::error::Error for ResidencyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ResidencyError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl From<std::io::Error> for ResidencyError {
+    fn from(err: std::io::Error) -> Self {
+        ResidencyError::Io(err)
+    }
+}
+
+/// Error returned when a slice doesn't have the length of the fixed-size secret built from it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LenError {
+    /// Length of the secret.
+    pub expected: usize,
+    /// Length of the slice.
+    pub actual: usize,
+}
+
+impl fmt::Display for LenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected a slice of length {}, got {}",
+            self.expected, self.actual
+        )
+    }
+}
+
+impl std::error::Error for LenError {}
+
+/// Error returned by [`ExposeSecret::try_expose_secret`](crate::ExposeSecret::try_expose_secret)
+/// and [`ExposeSecret::try_expose_secret_mut`](crate::ExposeSecret::try_expose_secret_mut).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExposeError {
+    /// The secret's pages couldn't be made accessible or excluded from core dumps again.
+    Protection(SecretBoxError),
+}
+
+impl fmt::Display for ExposeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExposeError::Protection(err) => write!(f, "failed to expose secret: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ExposeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ExposeError::Protection(err) => Some(err),
+        }
+    }
+}
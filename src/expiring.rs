@@ -0,0 +1,357 @@
+use core::fmt::{self, Debug};
+use std::{
+    any,
+    ops::Deref,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+#[cfg(feature = "async")]
+use std::{future::Future, pin::Pin};
+
+use zeroize::Zeroize;
+
+use crate::{ExposeSecret, SecretBox, SecretGuard};
+
+/// Secret that is zeroized and unlocked after it hasn't been accessed for a while.
+///
+/// The secret is loaded with a user supplied closure on first access, and reloaded the same
+/// way when it's accessed again after it expired. This keeps the time a secret spends in
+/// memory, and hence the window for extracting it from RAM, as short as possible.
+///
+/// Expiry is checked lazily on every access, call [`Self::expire_if_idle`] periodically to
+/// drop an idle secret without accessing it.
+///
+/// Not available on `wasm32-unknown-unknown`, which has no clock to measure idle time with.
+pub struct ExpiringSecretBox<S: Zeroize> {
+    ttl: Duration,
+    loader: Box<dyn Fn() -> S + Send + Sync>,
+    state: Mutex<State<S>>,
+}
+
+struct State<S: Zeroize> {
+    secret: Option<SecretBox<S>>,
+    last_access: Instant,
+}
+
+impl<S: Zeroize> State<S> {
+    fn is_expired(&self, ttl: Duration, now: Instant) -> bool {
+        self.secret.is_some() && now.saturating_duration_since(self.last_access) >= ttl
+    }
+}
+
+impl<S: Zeroize> ExpiringSecretBox<S> {
+    /// Create a secret that is loaded by `loader` and expires after being idle for `ttl`.
+    ///
+    /// The secret isn't loaded until it's first exposed.
+    pub fn new(ttl: Duration, loader: impl Fn() -> S + Send + Sync + 'static) -> Self {
+        Self {
+            ttl,
+            loader: Box::new(loader),
+            state: Mutex::new(State {
+                secret: None,
+                last_access: Instant::now(),
+            }),
+        }
+    }
+
+    /// Expose the secret, reloading it if it isn't loaded or has expired.
+    ///
+    /// Every call resets the idle timer. The secret stays loaded while the returned guard is
+    /// alive, other threads trying to expose it wait until it's dropped.
+    ///
+    /// # Deadlocks
+    ///
+    /// The guard holds the secret's lock, so calling this or any other method of the same
+    /// secret on the same thread while a guard is alive never returns:
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use shush_rs::ExpiringSecretBox;
+    ///
+    /// let secret = ExpiringSecretBox::new(Duration::from_secs(60), || String::from("hunter2"));
+    /// let first = secret.expose_secret();
+    /// let second = secret.expose_secret(); // deadlocks
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the reloaded secret's memory can't be locked or the secret can't be made
+    /// accessible, see [`ExposeSecret::expose_secret`].
+    pub fn expose_secret(&self) -> ExpiringSecretGuard<'_, S> {
+        let mut state = self.lock_state();
+        if state.is_expired(self.ttl, Instant::now()) {
+            state.secret = None;
+        }
+        if state.secret.is_none() {
+            state.secret = Some(SecretBox::new(Box::new((self.loader)())));
+        }
+        state.last_access = Instant::now();
+
+        let secret: *const SecretBox<S> = state.secret.as_ref().expect("secret is loaded");
+        // SAFETY: the box lives in the mutex, not in the `MutexGuard`, so it stays put when
+        // the guard is moved. Only `guard` can reach it until the lock is released, which
+        // happens after `guard` is dropped.
+        let guard = unsafe { &*secret }.expose_secret();
+        ExpiringSecretGuard {
+            guard,
+            _state: state,
+        }
+    }
+
+    /// Zeroize and unlock the secret if it has been idle for longer than the ttl.
+    ///
+    /// Returns whether the secret was dropped.
+    pub fn expire_if_idle(&self) -> bool {
+        let mut state = self.lock_state();
+        if state.is_expired(self.ttl, Instant::now()) {
+            state.secret = None;
+            return true;
+        }
+        false
+    }
+
+    /// Zeroize and unlock the secret right away, it will be reloaded on the next access.
+    pub fn expire(&self) {
+        self.lock_state().secret = None;
+    }
+
+    /// Whether the secret is currently loaded in memory.
+    pub fn is_loaded(&self) -> bool {
+        self.lock_state().secret.is_some()
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, State<S>> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl<S: Zeroize> Debug for ExpiringSecretBox<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ExpiringSecretBox<{}>([REDACTED])",
+            any::type_name::<S>()
+        )
+    }
+}
+
+/// Guard that keeps an [`ExpiringSecretBox`] loaded while it exposes the secret.
+pub struct ExpiringSecretGuard<'a, S: Zeroize> {
+    // Dropped first, so the secret is concealed again before the lock is released
+    guard: SecretGuard<'a, S>,
+    _state: MutexGuard<'a, State<S>>,
+}
+
+impl<S: Zeroize> Deref for ExpiringSecretGuard<'_, S> {
+    type Target = S;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+/// Boxed future returned by the loader of an [`AsyncExpiringSecretBox`].
+#[cfg(feature = "async")]
+type LoadFuture<S> = Pin<Box<dyn Future<Output = S> + Send>>;
+
+/// [`ExpiringSecretBox`] whose secret is loaded asynchronously, e.g. from a KMS or vault.
+///
+/// Concurrent accesses to an expired secret are coalesced, only the first one runs the
+/// loader while the others wait for its result. The state is guarded by an async mutex, so
+/// this works with any executor. Idle time is measured on tokio's clock, which falls back to
+/// the system clock outside a tokio runtime and follows `tokio::time::pause` in tests.
+#[cfg(feature = "async")]
+pub struct AsyncExpiringSecretBox<S: Zeroize> {
+    ttl: Duration,
+    loader: Box<dyn Fn() -> LoadFuture<S> + Send + Sync>,
+    state: tokio::sync::Mutex<State<S>>,
+}
+
+#[cfg(feature = "async")]
+impl<S: Zeroize> AsyncExpiringSecretBox<S> {
+    /// Create a secret that is loaded by awaiting `loader` and expires after being idle for
+    /// `ttl`.
+    ///
+    /// The secret isn't loaded until it's first exposed.
+    pub fn new<F, Fut>(ttl: Duration, loader: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = S> + Send + 'static,
+    {
+        Self {
+            ttl,
+            loader: Box::new(move || Box::pin(loader())),
+            state: tokio::sync::Mutex::new(State {
+                secret: None,
+                last_access: now(),
+            }),
+        }
+    }
+
+    /// Expose the secret, awaiting a reload if it isn't loaded or has expired.
+    ///
+    /// Every call resets the idle timer. Other tasks exposing the secret wait until the
+    /// returned guard is dropped.
+    ///
+    /// # Deadlocks
+    ///
+    /// The guard holds the secret's lock, so awaiting this or any other method of the same
+    /// secret in the task holding a guard never completes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the reloaded secret's memory can't be locked or the secret can't be made
+    /// accessible, see [`ExposeSecret::expose_secret`].
+    pub async fn expose_secret(&self) -> AsyncExpiringSecretGuard<'_, S> {
+        let mut state = self.state.lock().await;
+        if state.is_expired(self.ttl, now()) {
+            state.secret = None;
+        }
+        if state.secret.is_none() {
+            // The lock is held while loading, so concurrent callers wait for this load
+            let secret = (self.loader)().await;
+            state.secret = Some(SecretBox::new(Box::new(secret)));
+        }
+        state.last_access = now();
+
+        let secret: *const SecretBox<S> = state.secret.as_ref().expect("secret is loaded");
+        // SAFETY: the box lives in the mutex, not in the `MutexGuard`, so it stays put when
+        // the guard is moved. Only `guard` can reach it until the lock is released, which
+        // happens after `guard` is dropped.
+        let guard = unsafe { &*secret }.expose_secret();
+        AsyncExpiringSecretGuard {
+            guard,
+            _state: state,
+        }
+    }
+
+    /// Zeroize and unlock the secret if it has been idle for longer than the ttl.
+    ///
+    /// Returns whether the secret was dropped.
+    pub async fn expire_if_idle(&self) -> bool {
+        let mut state = self.state.lock().await;
+        if state.is_expired(self.ttl, now()) {
+            state.secret = None;
+            return true;
+        }
+        false
+    }
+
+    /// Zeroize and unlock the secret right away, it will be reloaded on the next access.
+    pub async fn expire(&self) {
+        self.state.lock().await.secret = None;
+    }
+
+    /// Whether the secret is currently loaded in memory.
+    pub async fn is_loaded(&self) -> bool {
+        self.state.lock().await.secret.is_some()
+    }
+}
+
+/// The current time on tokio's clock, which tests can pause and advance.
+#[cfg(feature = "async")]
+fn now() -> Instant {
+    tokio::time::Instant::now().into_std()
+}
+
+#[cfg(feature = "async")]
+impl<S: Zeroize> Debug for AsyncExpiringSecretBox<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "AsyncExpiringSecretBox<{}>([REDACTED])",
+            any::type_name::<S>()
+        )
+    }
+}
+
+/// Guard that keeps an [`AsyncExpiringSecretBox`] loaded while it exposes the secret.
+#[cfg(feature = "async")]
+pub struct AsyncExpiringSecretGuard<'a, S: Zeroize> {
+    // Dropped first, so the secret is concealed again before the lock is released
+    guard: SecretGuard<'a, S>,
+    _state: tokio::sync::MutexGuard<'a, State<S>>,
+}
+
+#[cfg(feature = "async")]
+impl<S: Zeroize> Deref for AsyncExpiringSecretGuard<'_, S> {
+    type Target = S;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    /// A secret that counts how often it's loaded.
+    fn counted_secret(ttl: Duration) -> (ExpiringSecretBox<String>, Arc<AtomicUsize>) {
+        let loads = Arc::new(AtomicUsize::new(0));
+        let secret = ExpiringSecretBox::new(ttl, {
+            let loads = loads.clone();
+            move || {
+                loads.fetch_add(1, Ordering::SeqCst);
+                String::from("Encrypted")
+            }
+        });
+        (secret, loads)
+    }
+
+    #[test]
+    fn test_expiring_secret_box_reloads_after_expiry() {
+        // Long enough that the secret can't go idle while the test runs
+        let (secret, loads) = counted_secret(Duration::from_secs(3600));
+        assert!(!secret.is_loaded());
+
+        assert_eq!(*secret.expose_secret(), "Encrypted");
+        assert_eq!(*secret.expose_secret(), "Encrypted");
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+        assert!(!secret.expire_if_idle());
+        assert!(secret.is_loaded());
+
+        let (secret, loads) = counted_secret(Duration::from_millis(20));
+        assert_eq!(*secret.expose_secret(), "Encrypted");
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(secret.expire_if_idle());
+        assert!(!secret.is_loaded());
+
+        assert_eq!(*secret.expose_secret(), "Encrypted");
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test(start_paused = true)]
+    async fn test_async_expiring_secret_box_coalesces_reloads() {
+        let loads = Arc::new(AtomicUsize::new(0));
+        let secret = AsyncExpiringSecretBox::new(Duration::from_millis(20), {
+            let loads = loads.clone();
+            move || {
+                let loads = loads.clone();
+                async move {
+                    tokio::task::yield_now().await;
+                    loads.fetch_add(1, Ordering::SeqCst);
+                    String::from("Encrypted")
+                }
+            }
+        });
+
+        let read = || async { secret.expose_secret().await.clone() };
+        let (a, b, c) = tokio::join!(read(), read(), read());
+        assert_eq!([a, b, c], ["Encrypted", "Encrypted", "Encrypted"]);
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+        tokio::time::advance(Duration::from_millis(40)).await;
+        assert!(secret.expire_if_idle().await);
+        assert!(!secret.is_loaded().await);
+        assert_eq!(*secret.expose_secret().await, "Encrypted");
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+    }
+}
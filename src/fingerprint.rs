@@ -0,0 +1,130 @@
+use core::fmt::{self, Display};
+use std::{
+    hash::{BuildHasher, Hash, RandomState},
+    sync::OnceLock,
+};
+
+use zeroize::Zeroize;
+
+use crate::{ExposeSecret, SecretBox, SecretString};
+
+/// Key of the hash behind [`SecretBox::hashed_fingerprint`], random per process.
+static FINGERPRINT_KEY: OnceLock<RandomState> = OnceLock::new();
+
+/// Key of the hash behind [`SecretBox::correlation_id`], independent of
+/// [`FINGERPRINT_KEY`] so logged ids reveal nothing about fingerprints.
+static CORRELATION_KEY: OnceLock<RandomState> = OnceLock::new();
+
+fn fingerprint<T: Hash + ?Sized>(value: &T) -> u64 {
+    FINGERPRINT_KEY
+        .get_or_init(RandomState::new)
+        .hash_one(value)
+}
+
+fn correlation_id<T: Hash + ?Sized>(value: &T) -> String {
+    let hash = CORRELATION_KEY
+        .get_or_init(RandomState::new)
+        .hash_one(value);
+    format!("{:08x}", hash >> 32)
+}
+
+impl<S: Zeroize + Hash + ?Sized> SecretBox<S> {
+    /// Keyed, non-reversible digest of the secret, suitable as a `HashMap` key.
+    ///
+    /// The secret is hashed with std's SipHash under a random key generated once per process,
+    /// so fingerprints can't be precomputed or compared across processes. SipHash's running
+    /// time depends only on the length of its input, not its contents, as long as `S`'s
+    /// `Hash` impl doesn't branch on the secret itself (the impls for integers, byte
+    /// arrays, `Vec<u8>` and `String` don't). With 64 bits, fingerprints are meant for lookups,
+    /// not as a replacement for comparing the secrets.
+    pub fn hashed_fingerprint(&self) -> u64 {
+        fingerprint(&*self.expose_secret())
+    }
+
+    /// Short id of the secret for log lines, e.g. to trace which of many secrets a code path
+    /// touched without logging the secret itself. Also printed by the `Display` impl.
+    ///
+    /// The id is 8 hex digits of a keyed hash like [`Self::hashed_fingerprint`]'s, under a
+    /// separate random key per process: equal secrets get the same id within a process, but
+    /// ids can't be compared across processes or restarts. With 32 bits, different secrets
+    /// may share an id. It's an aid for debugging only and must never be used as a token,
+    /// key or proof of knowing the secret.
+    ///
+    /// ```
+    /// use shush_rs::SecretBox;
+    ///
+    /// let token = SecretBox::new(Box::new([7u8; 32]));
+    /// let id = token.correlation_id();
+    /// assert_eq!(id.len(), 8);
+    /// assert_eq!(token.to_string(), format!("[REDACTED {}]", id));
+    /// ```
+    pub fn correlation_id(&self) -> String {
+        correlation_id(&*self.expose_secret())
+    }
+}
+
+impl<S: Zeroize + Hash + ?Sized> Display for SecretBox<S> {
+    /// Print `[REDACTED <id>]` with the secret's [`SecretBox::correlation_id`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[REDACTED {}]", self.correlation_id())
+    }
+}
+
+impl SecretString {
+    /// Keyed, non-reversible digest of the string, see [`SecretBox::hashed_fingerprint`].
+    pub fn hashed_fingerprint(&self) -> u64 {
+        fingerprint(&*self.expose_secret())
+    }
+
+    /// Short id of the string for log lines, see [`SecretBox::correlation_id`].
+    pub fn correlation_id(&self) -> String {
+        correlation_id(&*self.expose_secret())
+    }
+}
+
+impl Display for SecretString {
+    /// Print `[REDACTED <id>]` with the string's [`SecretString::correlation_id`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[REDACTED {}]", self.correlation_id())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hashed_fingerprint() {
+        let secret_a = SecretBox::new(Box::new([1u8; 32]));
+        let secret_b = SecretBox::new(Box::new([1u8; 32]));
+        let secret_c = SecretBox::new(Box::new([2u8; 32]));
+
+        assert_eq!(secret_a.hashed_fingerprint(), secret_b.hashed_fingerprint());
+        assert_ne!(secret_a.hashed_fingerprint(), secret_c.hashed_fingerprint());
+        assert_eq!(
+            SecretString::from(String::from("token")).hashed_fingerprint(),
+            SecretString::from(String::from("token")).hashed_fingerprint()
+        );
+    }
+
+    #[test]
+    fn test_correlation_id() {
+        let secret_a = SecretBox::new(Box::new([1u8; 32]));
+        let secret_b = SecretBox::new(Box::new([1u8; 32]));
+        let secret_c = SecretBox::new(Box::new([2u8; 32]));
+
+        let id = secret_a.correlation_id();
+        assert_eq!(id.len(), 8);
+        assert!(id.bytes().all(|byte| byte.is_ascii_hexdigit()));
+        assert_eq!(id, secret_b.correlation_id());
+        assert_ne!(id, secret_c.correlation_id());
+        assert_eq!(format!("{}", secret_a), format!("[REDACTED {}]", id));
+
+        let string = SecretString::from(String::from("token"));
+        assert_eq!(
+            string.to_string(),
+            format!("[REDACTED {}]", string.correlation_id())
+        );
+        assert!(!string.to_string().contains("token"));
+    }
+}
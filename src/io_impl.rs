@@ -0,0 +1,145 @@
+use std::{
+    fs::File,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+use crate::{ExposeSecret, SecretVec};
+
+/// Capacity of the buffer [`SecretVec::from_reader`] starts reading into.
+const INITIAL_CAPACITY: usize = 256;
+
+impl SecretVec<u8> {
+    /// Read `reader` to the end into a new secret vector.
+    ///
+    /// The bytes are read straight into a locked buffer, no temporary buffers are used. When
+    /// the buffer is full its contents are copied into a larger locked buffer and the old one
+    /// is zeroized, so the plaintext never ends up in unlocked or unzeroized memory.
+    ///
+    /// If reading fails, everything read so far is zeroized before the error is returned.
+    pub fn from_reader(mut reader: impl Read) -> io::Result<Self> {
+        Self::read_locked(&mut reader, INITIAL_CAPACITY)
+    }
+
+    /// Read the file at `path` into a new secret vector, see [`Self::from_reader`].
+    ///
+    /// The locked buffer is sized after the file's length, so it usually doesn't need to grow.
+    pub fn from_path(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        let capacity = file.metadata().map_or(INITIAL_CAPACITY, |metadata| {
+            // One extra byte to detect the end of the file without growing
+            usize::try_from(metadata.len())
+                .unwrap_or(0)
+                .saturating_add(1)
+        });
+        Self::read_locked(&mut file, capacity)
+    }
+
+    fn read_locked(reader: &mut impl Read, capacity: usize) -> io::Result<Self> {
+        let mut secret = Self::try_new_with_len(capacity.max(1)).map_err(io::Error::other)?;
+        let mut filled = 0;
+
+        loop {
+            if filled == secret.expose_secret().len() {
+                let mut grown = Self::try_new_with_len(filled * 2).map_err(io::Error::other)?;
+                grown.expose_secret_mut()[..filled]
+                    .copy_from_slice(&secret.expose_secret()[..filled]);
+                secret = grown;
+            }
+
+            let mut buf = secret.expose_secret_mut();
+            match reader.read(&mut buf[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        // Truncating keeps the capacity, so the bytes stay in the locked buffer
+        secret.expose_secret_mut().truncate(filled);
+        Ok(secret)
+    }
+}
+
+/// Appends to the vector, growing it into locked buffers like
+/// [`SecretVec::extend_locked`](crate::SecretVec::extend_locked), e.g. to stream decrypted
+/// data in with [`io::copy`] without an unlocked staging buffer.
+///
+/// ```
+/// use std::io::Write;
+///
+/// use shush_rs::{ExposeSecret, SecretVec};
+///
+/// let mut plaintext = SecretVec::from(Vec::new());
+/// plaintext.write_all(b"chunk one, ")?;
+/// plaintext.write_all(b"chunk two")?;
+/// assert_eq!(*plaintext.expose_secret(), b"chunk one, chunk two");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// Writing fails if a larger buffer can't be locked, the vector keeps what was written before.
+impl Write for SecretVec<u8> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.try_reserve_locked(buf.len())
+            .map_err(io::Error::other)?;
+        self.expose_secret_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LockableRegion;
+
+    #[test]
+    fn test_from_reader() {
+        let data: Vec<u8> = (0..=255).cycle().take(INITIAL_CAPACITY * 3 + 7).collect();
+        let secret_vec = SecretVec::from_reader(data.as_slice()).unwrap();
+        assert_eq!(*secret_vec.expose_secret(), data);
+
+        let heap_region = secret_vec.heap_region.expect("buffer is not locked");
+        let ptr = secret_vec.expose_secret().as_ptr() as usize;
+        assert!(
+            heap_region.start <= ptr && ptr + data.len() <= heap_region.start + heap_region.len
+        );
+    }
+
+    #[test]
+    fn test_from_path() {
+        let path = std::env::temp_dir().join(format!("shush-rs-{}.key", std::process::id()));
+        File::create(&path)
+            .and_then(|mut file| file.write_all(b"private key"))
+            .unwrap();
+
+        let secret_vec = SecretVec::from_path(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(*secret_vec.unwrap().expose_secret(), b"private key");
+    }
+
+    #[test]
+    fn test_write_chunks() {
+        let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let mut secret_vec = SecretVec::from(Vec::new());
+        for chunk in data.chunks(77) {
+            secret_vec.write_all(chunk).unwrap();
+        }
+        secret_vec.flush().unwrap();
+        assert_eq!(*secret_vec.expose_secret(), data);
+
+        // The whole buffer, spare capacity included, is locked
+        let heap_region = secret_vec.heap_region.expect("buffer is not locked");
+        let (ptr, len) = secret_vec.expose_secret().region();
+        let ptr = ptr as usize;
+        assert!(heap_region.start <= ptr && ptr + len <= heap_region.start + heap_region.len);
+
+        let mut copied = SecretVec::from(Vec::new());
+        io::copy(&mut data.as_slice(), &mut copied).unwrap();
+        assert_eq!(*copied.expose_secret(), data);
+    }
+}
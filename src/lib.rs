@@ -6,340 +6,2510 @@
 
 use core::{
     any,
+    convert::Infallible,
     fmt::{self, Debug},
 };
+use std::mem::{self, align_of, size_of, size_of_val, ManuallyDrop, MaybeUninit};
 use std::{
+    alloc::{self, Layout},
     fmt::Display,
-    ops::{Deref, DerefMut},
+    ops::{Deref, DerefMut, Range},
+    pin::Pin,
+    ptr::{self, NonNull},
+    str::Utf8Error,
+    sync::{Mutex, RwLock},
 };
-use std::{mem::size_of_val, str::FromStr};
-
-#[cfg(unix)]
-use std::cell::LazyCell;
-
-#[cfg(unix)]
-use errno::errno;
-
-#[cfg(unix)]
-use std::ffi::c_void;
-
-#[cfg(unix)]
-use libc::{mlock, munlock, sysconf, _SC_PAGESIZE};
-
-#[cfg(target_os = "linux")]
-use libc::{madvise, MADV_DODUMP, MADV_DONTDUMP};
 
+#[cfg(feature = "rand")]
+pub use getrandom;
+#[cfg(feature = "secrecy-compat")]
+pub use secrecy;
+#[cfg(feature = "smallvec")]
+pub use smallvec;
+#[cfg(feature = "subtle")]
+pub use subtle;
 pub use zeroize;
+use zeroize::{DefaultIsZeroes, Zeroizing};
 pub use zeroize::{Zeroize, ZeroizeOnDrop};
 
+#[cfg(all(unix, feature = "atfork"))]
+mod atfork;
+mod builder;
+mod error;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+mod expiring;
+mod fingerprint;
+mod io_impl;
+mod map;
+mod maybe;
+mod memory;
+mod mutex;
+mod observer;
+mod pattern;
+#[cfg(feature = "rand")]
+mod rand_impl;
+mod read_only;
+mod scratch;
+#[cfg(feature = "secrecy-compat")]
+mod secrecy_impl;
+#[cfg(feature = "serde")]
+mod serde_impl;
+#[cfg(feature = "smallvec")]
+mod smallvec_impl;
+mod string;
+#[cfg(feature = "subtle")]
+mod subtle_impl;
+
+pub use builder::SecretBoxBuilder;
+use error::fail;
+#[cfg(target_os = "linux")]
+pub use error::ResidencyError;
+pub use error::{ExposeError, LenError, SecretBoxError, ZeroizeVerifyError};
+#[cfg(all(
+    feature = "async",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+pub use expiring::{AsyncExpiringSecretBox, AsyncExpiringSecretGuard};
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub use expiring::{ExpiringSecretBox, ExpiringSecretGuard};
+pub use map::SecretMap;
+pub use maybe::{MaybeSecret, MaybeSecretGuard};
+pub use mutex::{SecretMutex, SecretMutexGuard};
+use pattern::OverwriteFn;
+pub use pattern::ZeroizePattern;
+pub use read_only::ReadOnlySecret;
+pub use scratch::ScratchSecret;
+#[cfg(feature = "smallvec")]
+pub use smallvec_impl::{SecretSmallVec, ZeroizeSmallVec};
+pub use string::SecretString;
+
 #[cfg(unix)]
-static mut PAGE_SIZE: LazyCell<i64> = LazyCell::new(|| {
-    {
-        let page_size = unsafe { sysconf(_SC_PAGESIZE) };
-        if page_size == -1 {
-            panic!("Error getting page size: \n {}", errno())
-        }
-        page_size
+pub use memory::disable_core_dumps;
+pub use memory::disable_memory_locking;
+pub use memory::dump_protection_available;
+pub use memory::set_memlock_budget;
+pub use observer::{set_observer, Observer};
+
+use memory::{lock_memory, relock_region, unlock_memory, Access, LockMode, Region};
+
+static DROP_ERROR_HOOK: RwLock<Option<fn(&SecretBoxError)>> = RwLock::new(None);
+
+/// Set a hook that is called with every error that occurs while a [`SecretBox`] is dropped.
+///
+/// Dropping a secret never panics, errors from unlocking its memory are passed to this hook
+/// instead. They are ignored if no hook is set.
+pub fn set_drop_error_hook(hook: fn(&SecretBoxError)) {
+    *DROP_ERROR_HOOK
+        .write()
+        .unwrap_or_else(|err| err.into_inner()) = Some(hook);
+}
+
+fn report_drop_error(err: &SecretBoxError) {
+    let hook = *DROP_ERROR_HOOK
+        .read()
+        .unwrap_or_else(|err| err.into_inner());
+    if let Some(hook) = hook {
+        hook(err)
     }
-});
+}
 
 /// Wrapper for the inner secret. Can be exposed by [`ExposeSecret`]
-pub struct SecretBox<S: Zeroize> {
-    inner_secret: Box<S>,
+///
+/// The secret lives in its own allocation, so moving a `SecretBox`, e.g. into a larger
+/// struct, only moves the handle: the secret's address stays the same until the box is
+/// dropped or [`SecretBox::into_inner`] is called. See [`SecretBox::as_pinned_ref`] to make
+/// that explicit.
+///
+/// A `SecretBox` is `Send` and `Sync` whenever `S` is. To share a secret read-only between
+/// threads, wrap it in an `Arc` and call [`ExposeSecret::expose_secret`] through it, which
+/// works by auto-deref. Reading is race-free: shared guards only hand out `&S`, and the page
+/// protection of a [`SecretBox::new_protected`] secret is switched under a lock, so the pages
+/// stay readable until the last guard of any thread is dropped.
+///
+/// ```
+/// use std::{sync::Arc, thread};
+///
+/// use shush_rs::{ExposeSecret, SecretString};
+///
+/// let api_key = Arc::new(SecretString::from(String::from("hunter2")));
+/// let workers: Vec<_> = (0..4)
+///     .map(|_| {
+///         let api_key = Arc::clone(&api_key);
+///         thread::spawn(move || api_key.expose_secret().len())
+///     })
+///     .collect();
+/// for worker in workers {
+///     assert_eq!(worker.join().unwrap(), 7);
+/// }
+/// ```
+///
+/// Like with `Box`, a secret that isn't `Send` can't be moved to another thread in a box
+/// either:
+///
+/// ```compile_fail
+/// use std::rc::Rc;
+///
+/// use shush_rs::{SecretBox, Zeroize};
+///
+/// struct Shared(Rc<[u8; 32]>);
+///
+/// impl Zeroize for Shared {
+///     fn zeroize(&mut self) {}
+/// }
+///
+/// let secret = SecretBox::new(Box::new(Shared(Rc::new([0u8; 32]))));
+/// std::thread::spawn(move || drop(secret));
+/// ```
+pub struct SecretBox<S: Zeroize + ?Sized> {
+    inner_secret: NonNull<S>,
+    backing: Backing,
+    region: Region,
+    /// Region of the heap buffer owned by the secret, if it is locked as well.
+    heap_region: Option<Region>,
+    /// Set if the secret's pages are inaccessible while it isn't exposed.
+    idle_protection: Option<IdleProtection>,
+    /// Tag printed by `Debug` next to the redacted secret.
+    debug_label: Option<&'static str>,
+    /// Reports the secret's heap data, set if it's locked with [`LockableRegion`].
+    region_of: Option<RegionOf>,
+    /// Protection applied to the secret, reused for its heap data and clones.
+    policy: SecretBoxBuilder,
+    /// Overwrites the secret before it's zeroized on drop, see [`SecretBox::with_zeroize_pattern`].
+    drop_pattern: Option<(ZeroizePattern, OverwriteFn)>,
 }
 
-impl<S: Zeroize> Zeroize for SecretBox<S> {
-    fn zeroize(&mut self) {
-        self.inner_secret.as_mut().zeroize()
-    }
+/// Type-erased [`LockableRegion::region`] of a secret.
+type RegionOf = unsafe fn(NonNull<()>) -> (*const u8, usize);
+
+unsafe fn region_of<S: LockableRegion>(secret: NonNull<()>) -> (*const u8, usize) {
+    secret.cast::<S>().as_ref().region()
 }
 
-/// Convenient type alias for Secret Wrapped Strings
-pub type SecretString = SecretBox<String>;
+#[cfg(test)]
+thread_local! {
+    /// Bytes of the last secret dropped on this thread, followed by its heap data if that's
+    /// locked with [`LockableRegion`], copied right after they were zeroized.
+    static ZEROIZED_ON_DROP: core::cell::RefCell<Vec<u8>> = const {
+        core::cell::RefCell::new(Vec::new())
+    };
+}
 
-impl FromStr for SecretString {
-    type Err = core::convert::Infallible;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(SecretBox::new(Box::new(s.to_string())))
+#[cfg(test)]
+impl<S: Zeroize + ?Sized> SecretBox<S> {
+    /// Record the zeroized secret for tests to check once its memory is freed.
+    unsafe fn record_zeroized(&self) {
+        let value = self.inner_secret.as_ref();
+        let mut bytes =
+            std::slice::from_raw_parts(ptr::from_ref(value).cast::<u8>(), size_of_val(value))
+                .to_vec();
+        if let Some(region_of) = self.region_of {
+            let (ptr, len) = region_of(self.inner_secret.cast());
+            bytes.extend_from_slice(std::slice::from_raw_parts(ptr, len));
+        }
+        ZEROIZED_ON_DROP.with(|recorded| *recorded.borrow_mut() = bytes);
     }
 }
 
-/// Convenient type alias for Secret Wrapped Vectors
-pub type SecretVec<T> = SecretBox<Vec<T>>;
-
-impl<T: Zeroize> SecretVec<T> {
-    /// Generate SecretVec from vec
-    pub fn from(new_vec: Vec<T>) -> Self {
-        SecretBox::new(Box::new(new_vec))
-    }
+#[cfg(all(unix, feature = "atfork"))]
+unsafe fn zeroize_erased<S: Zeroize>(secret: NonNull<()>) {
+    secret.cast::<S>().as_mut().zeroize()
 }
 
-impl<S: Zeroize> Drop for SecretBox<S> {
-    fn drop(&mut self) {
-        let len = size_of_val(&*self.inner_secret);
-        let secret_ptr = self.inner_secret.as_ref() as *const S;
+// SAFETY: `SecretBox` owns the secret just like a `Box` does, the `NonNull` only opts out
+// of the auto traits. The only shared state is the exposure count of `IdleProtection`, which
+// is behind a `Mutex`. Locking, dump exclusion and page protection apply to the process'
+// address space rather than to a thread, so they stay in effect and are undone correctly no
+// matter which thread creates, exposes or drops the secret. Zeroizing on drop needs
+// `&mut self`, which no other thread can hold at the same time.
+unsafe impl<S: Zeroize + Send + ?Sized> Send for SecretBox<S> {}
+unsafe impl<S: Zeroize + Sync + ?Sized> Sync for SecretBox<S> {}
+
+/// Memory holding the secret.
+#[derive(Debug, Clone, Copy)]
+enum Backing {
+    /// Allocated by the global allocator as a `Box<S>`.
+    Heap,
+    /// Pages mapped for the secret alone.
+    Pages {
+        start: usize,
+        len: usize,
+        /// Whether the pages were mapped with `memfd_secret(2)`.
+        secret_memory: bool,
+    },
+    /// Whole pages allocated by the global allocator for the secret alone.
+    Aligned { layout: Layout },
+    /// Memory owned by the caller, see [`SecretBox::from_locked_ptr`].
+    External,
+}
 
-        #[cfg(unix)]
-        {
-            let page_size = unsafe { *PAGE_SIZE as usize };
-            // Align the address and size to the page boundary
-            let start = (secret_ptr as usize) & !(page_size - 1);
-            let end = ((secret_ptr as usize) + len + page_size - 1) & !(page_size - 1);
-            let aligned_len = end - start;
+/// Kind of memory holding a secret, see [`SecretBox::backing_kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BackingKind {
+    /// A regular heap allocation, e.g. from [`SecretBox::new`].
+    Heap,
+    /// Pages mapped for the secret alone, e.g. from [`SecretBox::new_locked_pages`].
+    Pages,
+    /// Pages mapped with `memfd_secret(2)`, see `SecretBox::new_memfd_secret`.
+    MemfdSecret,
+    /// Whole pages from the global allocator, see [`SecretBox::new_page_aligned`].
+    PageAligned,
+    /// Memory owned by the caller, see [`SecretBox::from_locked_ptr`].
+    External,
+}
 
-            unsafe {
-                #[cfg(target_os = "linux")]
-                if madvise(start as *mut c_void, aligned_len, MADV_DODUMP) != 0 {
-                    panic!("madvise failed: \n{:?}", errno());
-                }
+/// Keeps the pages of a secret inaccessible while no guard exposes it.
+struct IdleProtection {
+    start: usize,
+    len: usize,
+    /// Number of guards currently exposing the secret.
+    exposed: Mutex<usize>,
+}
 
-                if munlock(start as *const c_void, aligned_len) != 0 {
-                    panic!("Unable to munlock variable: \n {:?} \n", errno())
-                }
-            }
+impl IdleProtection {
+    fn expose(&self, access: Access) -> Result<(), SecretBoxError> {
+        let mut exposed = self.exposed.lock().unwrap_or_else(|err| err.into_inner());
+        // Only the first reader has to make the pages readable, writers are exclusive
+        if *exposed == 0 || access == Access::ReadWrite {
+            unsafe { memory::protect_memory(self.start, self.len, access)? };
         }
+        *exposed += 1;
+        Ok(())
+    }
 
-        #[cfg(windows)]
-        unsafe {
-            if windows_sys::Win32::System::Memory::VirtualUnlock(secret_ptr.cast(), len) == 0 {
-                panic!("VirtualUnlock failed",);
-            }
+    fn conceal(&self) -> Result<(), SecretBoxError> {
+        let mut exposed = self.exposed.lock().unwrap_or_else(|err| err.into_inner());
+        *exposed -= 1;
+        if *exposed == 0 {
+            unsafe { memory::protect_memory(self.start, self.len, Access::None)? };
         }
-
-        self.zeroize()
+        Ok(())
     }
 }
 
-impl<S: Zeroize> ZeroizeOnDrop for SecretBox<S> {}
-
-impl<S: Zeroize> From<Box<S>> for SecretBox<S> {
-    fn from(source: Box<S>) -> Self {
-        Self::new(source)
+impl<S: Zeroize + ?Sized> Zeroize for SecretBox<S> {
+    fn zeroize(&mut self) {
+        self.expose_secret_mut().zeroize()
     }
 }
 
-impl<S: Zeroize> SecretBox<S> {
-    /// Create a secret value using a pre-boxed value.
-    pub fn new(boxed_secret: Box<S>) -> Self {
-        let len = size_of_val(&*boxed_secret);
-
-        let secret_ptr = Box::into_raw(boxed_secret);
-
-        #[cfg(unix)]
-        {
-            let page_size = unsafe { *PAGE_SIZE as usize };
+/// Convenient type alias for Secret Wrapped Vectors
+pub type SecretVec<T> = SecretBox<Vec<T>>;
 
-            // Align the address and size to the page boundary
-            let start = (secret_ptr as usize) & !(page_size - 1);
-            let end = ((secret_ptr as usize) + len + page_size - 1) & !(page_size - 1);
-            let aligned_len = end - start;
+impl<T: Zeroize> SecretVec<T> {
+    /// Generate SecretVec from vec
+    ///
+    /// Besides the `Vec` itself this locks the heap buffer holding its elements, see
+    /// [`SecretBox::new_with_region`] for how growing the vector is handled.
+    pub fn from(new_vec: Vec<T>) -> Self {
+        SecretBox::new_with_region(Box::new(new_vec))
+    }
 
-            unsafe {
-                #[cfg(target_os = "linux")]
-                if madvise(start as *mut c_void, aligned_len, MADV_DONTDUMP) != 0 {
-                    panic!("madvise failed: \n{:?}", errno());
-                }
-                if mlock(start as *const c_void, aligned_len) != 0 {
-                    panic!("mlock failed: \n{:?}", errno());
-                }
-            }
-        }
+    /// Make room for at least `additional` more elements in a locked buffer, e.g. before
+    /// appending decrypted data piece by piece.
+    ///
+    /// Growing the vector through [`ExposeSecret::expose_secret_mut`] lets the allocator move
+    /// the elements to an unlocked buffer and free the old one without zeroizing it. Instead,
+    /// if the spare capacity doesn't suffice, this moves the elements to a new buffer that is
+    /// locked first and zeroizes the old one. Like [`Vec::reserve`] the capacity at least
+    /// doubles, so repeated small reservations stay cheap.
+    ///
+    /// ```
+    /// use shush_rs::{ExposeSecret, SecretVec};
+    ///
+    /// let mut plaintext = SecretVec::from(Vec::new());
+    /// for chunk in [[1u8; 16], [2u8; 16]] {
+    ///     plaintext.extend_locked(chunk);
+    /// }
+    /// assert_eq!(plaintext.expose_secret().len(), 32);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new buffer can't be locked or the capacity overflows `usize`. See
+    /// [`Self::try_reserve_locked`] for a non-panicking alternative.
+    pub fn reserve_locked(&mut self, additional: usize) {
+        self.try_reserve_locked(additional)
+            .unwrap_or_else(|err| fail(err))
+    }
 
-        #[cfg(windows)]
-        unsafe {
-            if windows_sys::Win32::System::Memory::VirtualLock(secret_ptr.cast(), len) == 0 {
-                panic!("VirtualLock failed",);
-            }
+    /// Same as [`Self::reserve_locked`], but returns an error if the new buffer can't be
+    /// locked. The vector is left as it was in that case.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity overflows `usize`.
+    pub fn try_reserve_locked(&mut self, additional: usize) -> Result<(), SecretBoxError> {
+        let (len, capacity) = self.with_exposed(|vec| (vec.len(), vec.capacity()));
+        // Spare capacity is only locked if the buffer is
+        if self.region_of.is_some() && capacity - len >= additional {
+            return Ok(());
         }
 
-        // Recreate Box from raw pointer
-        let inner_secret = unsafe { Box::from_raw(secret_ptr) };
+        let required = len.checked_add(additional).expect("capacity overflow");
+        let mut grown = SecretVec::try_new_with_region(Box::new(Vec::with_capacity(
+            required.max(capacity.saturating_mul(2)),
+        )))?;
+        grown.with_exposed_mut(|vec| vec.extend(self.expose_secret_mut().drain(..)));
+        self.replace(grown);
+        Ok(())
+    }
 
-        Self { inner_secret }
+    /// Append `value`, growing the vector into a locked buffer, see [`Self::reserve_locked`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new buffer can't be locked.
+    pub fn push_locked(&mut self, value: T) {
+        self.reserve_locked(1);
+        self.expose_secret_mut().push(value);
     }
-}
 
-impl<S: Zeroize + Default> SecretBox<S> {
-    /// Create a secret value using a function that can initialize the vale in-place.
-    pub fn new_with_mut(ctr: impl FnOnce(&mut S)) -> Self {
-        let mut secret = Self::default();
-        ctr(&mut *secret.expose_secret_mut());
-        secret
+    /// Append the elements of `iter`, growing the vector into locked buffers, see
+    /// [`Self::reserve_locked`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if a new buffer can't be locked.
+    pub fn extend_locked(&mut self, iter: impl IntoIterator<Item = T>) {
+        let iter = iter.into_iter();
+        self.reserve_locked(iter.size_hint().0);
+        let mut vec = self.expose_secret_mut();
+        for value in iter {
+            if vec.len() == vec.capacity() {
+                drop(vec);
+                self.reserve_locked(1);
+                vec = self.expose_secret_mut();
+            }
+            vec.push(value);
+        }
     }
 }
 
-impl<S: Zeroize + Clone> SecretBox<S> {
-    /// Create a secret value using the provided function as a constructor.
+impl<T: DefaultIsZeroes> SecretVec<T> {
+    /// Create a secret vector of `len` zeros, e.g. to receive a decrypted payload
     ///
-    /// The implementation makes an effort to zeroize the locally constructed value
-    /// before it is copied to the heap, and constructing it inside the closure minimizes
-    /// the possibility of it being accidentally copied by other code.
+    /// The vector's heap buffer is locked before anything is written to it.
     ///
-    /// **Note:** using [`Self::new`] or [`Self::new_with_mut`] is preferable when possible,
-    /// since this method's safety relies on empyric evidence and may be violated on some targets.
-    pub fn new_with_ctr(ctr: impl FnOnce() -> S) -> Self {
-        let mut data = ctr();
-        let secret = Self::new(Box::new(data.clone()));
-        data.zeroize();
-        secret
+    /// # Panics
+    ///
+    /// Panics if the secret's memory can't be locked. See [`Self::try_new_with_len`] for a
+    /// non-panicking alternative.
+    pub fn new_with_len(len: usize) -> Self {
+        Self::try_new_with_len(len).unwrap_or_else(|err| fail(err))
     }
 
-    /// Same as [`Self::new_with_ctr`], but the constructor can be fallible.
+    /// Same as [`Self::new_with_len`], but returns an error if the secret's memory can't be
+    /// locked.
+    pub fn try_new_with_len(len: usize) -> Result<Self, SecretBoxError> {
+        SecretBox::try_new_with_region(Box::new(vec![T::default(); len]))
+    }
+
+    /// Create an empty secret vector with room for `capacity` elements, e.g. to lock a
+    /// buffer at startup and fill it later on a latency-sensitive path.
     ///
+    /// The whole capacity is zeroed, which faults its pages in, and locked right away. Filling
+    /// the vector within its capacity, e.g. with `push` through
+    /// [`ExposeSecret::expose_secret_mut`] or with [`std::io::Write`], then needs no further
+    /// `mlock`. Use [`Self::new_with_len`] instead to fill the buffer in place with
+    /// [`SecretBox::with_mut_ptr`].
     ///
-    /// **Note:** using [`Self::new`] or [`Self::new_with_mut`] is preferable when possible,
-    /// since this method's safety relies on empyric evidence and may be violated on some targets.
-    pub fn try_new_with_ctr<E>(ctr: impl FnOnce() -> Result<S, E>) -> Result<Self, E> {
-        let mut data = ctr()?;
-        let secret = Self::new(Box::new(data.clone()));
-        data.zeroize();
-        Ok(secret)
+    /// ```
+    /// use std::io::Write;
+    ///
+    /// use shush_rs::{ExposeSecret, SecretVec};
+    ///
+    /// let mut session_key = SecretVec::with_capacity_locked(32);
+    /// // Later, on the hot path
+    /// session_key.write_all(&[7; 32]).unwrap();
+    /// assert_eq!(*session_key.expose_secret(), [7; 32]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret's memory can't be locked. See [`Self::try_with_capacity_locked`]
+    /// for a non-panicking alternative.
+    pub fn with_capacity_locked(capacity: usize) -> Self {
+        Self::try_with_capacity_locked(capacity).unwrap_or_else(|err| fail(err))
     }
-}
 
-impl<S: Zeroize + Default> Default for SecretBox<S> {
-    fn default() -> Self {
-        let inner_secret = Box::<S>::default();
-        SecretBox::new(inner_secret)
+    /// Same as [`Self::with_capacity_locked`], but returns an error if the secret's memory
+    /// can't be locked.
+    pub fn try_with_capacity_locked(capacity: usize) -> Result<Self, SecretBoxError> {
+        let mut vec = Vec::with_capacity(capacity);
+        vec.spare_capacity_mut()
+            .fill(MaybeUninit::new(T::default()));
+        SecretBox::try_new_with_region(Box::new(vec))
     }
-}
 
-impl<S: Zeroize> Debug for SecretBox<S> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "SecretBox<{}>([REDACTED])", any::type_name::<S>())
+    /// Copy `src` into a new secret vector and zeroize `src`, e.g. an FFI buffer or a
+    /// decoding staging area.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret's memory can't be locked, `src` is zeroized either way.
+    pub fn from_slice_zeroizing(src: &mut [T]) -> Self {
+        let secret = Self::try_new_with_len(src.len()).map(|mut secret| {
+            secret.expose_secret_mut().copy_from_slice(src);
+            secret
+        });
+        src.zeroize();
+        secret.unwrap_or_else(|err| fail(err))
     }
 }
 
-impl<S> Clone for SecretBox<S>
-where
-    S: CloneableSecret,
-{
-    fn clone(&self) -> Self {
-        SecretBox::new(self.inner_secret.clone())
+impl SecretVec<u8> {
+    /// Lend the secret bytes to `f`, e.g. to pass a key to a crypto API taking `&[u8]`.
+    ///
+    /// The slice can't outlive `f`, the secret is concealed again once it returns like with
+    /// [`SecretBox::with_exposed`].
+    pub fn with_bytes<R>(&self, f: impl FnOnce(&[u8]) -> R) -> R {
+        self.with_exposed(|bytes| f(bytes))
     }
-}
 
-impl<S: Zeroize> ExposeSecret<S> for SecretBox<S> {
-    fn expose_secret(&self) -> SecretGuard<'_, S> {
-        SecretGuard::new(&self.inner_secret)
+    /// Turn the bytes into a [`SecretString`], e.g. a password read as bytes.
+    ///
+    /// The string reuses the vector's buffer, which stays locked, the bytes aren't copied. If
+    /// they aren't valid UTF-8 the secret is zeroized and the error returned.
+    ///
+    /// ```
+    /// use shush_rs::{ExposeSecret, SecretVec};
+    ///
+    /// let password = SecretVec::from(b"hunter2".to_vec()).into_string().unwrap();
+    /// assert_eq!(&*password.expose_secret(), "hunter2");
+    /// assert!(SecretVec::from(vec![0xff]).into_string().is_err());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the string's memory can't be locked.
+    pub fn into_string(self) -> Result<SecretString, Utf8Error> {
+        self.with_bytes(|bytes| str::from_utf8(bytes).map(drop))?;
+        // SAFETY: the bytes were just checked to be valid UTF-8
+        let inner = self.convert_with_region(|bytes| unsafe { String::from_utf8_unchecked(bytes) });
+        Ok(SecretString::from_inner(inner))
     }
 
-    fn expose_secret_mut(&mut self) -> SecretGuardMut<'_, S> {
-        SecretGuardMut::new(&mut self.inner_secret)
+    /// Append the bytes of `other`, e.g. to build KDF input like `salt || info || key`.
+    ///
+    /// The bytes are copied straight from one locked buffer to the other. If they don't fit
+    /// into the spare capacity, the vector first grows into a new locked buffer, see
+    /// [`SecretVec::reserve_locked`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new buffer can't be locked.
+    pub fn extend_from_secret(&mut self, other: &SecretVec<u8>) {
+        let other = other.expose_secret();
+        self.reserve_locked(other.len());
+        self.expose_secret_mut().extend_from_slice(&other);
     }
 }
 
-/// Secret Guard that holds a reference to the secret.
-#[derive(Debug, Eq, PartialEq)]
-pub struct SecretGuard<'a, S>
-where
-    S: Zeroize,
-{
-    data: &'a S,
-}
+/// Convenient type alias for fixed-size secrets such as symmetric keys
+///
+/// The array is stored inline in the locked allocation, so exactly `N` elements are locked.
+pub type SecretArray<T, const N: usize> = SecretBox<[T; N]>;
 
-impl<S: Display + Zeroize> Display for SecretGuard<'_, S> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.data)
+impl<T: DefaultIsZeroes, const N: usize> SecretArray<T, N> {
+    /// Create a secret array with all elements set to zero
+    pub fn zeroed() -> Self {
+        SecretBox::new(Box::new([T::default(); N]))
     }
-}
-
-impl<S> Deref for SecretGuard<'_, S>
-where
-    S: Zeroize,
-{
-    type Target = S;
 
-    fn deref(&self) -> &Self::Target {
-        self.data
+    /// Copy `slice` into a new secret array
+    ///
+    /// The elements are copied straight into the locked allocation. Fails if the slice isn't
+    /// exactly `N` elements long.
+    pub fn from_slice(slice: &[T]) -> Result<Self, LenError> {
+        if slice.len() != N {
+            return Err(LenError {
+                expected: N,
+                actual: slice.len(),
+            });
+        }
+        let mut secret_array = Self::zeroed();
+        secret_array.expose_secret_mut().copy_from_slice(slice);
+        Ok(secret_array)
     }
 }
 
-/// Secret Guard that holds a mutable to reference to the secret.
-#[derive(Debug, Eq, PartialEq)]
-pub struct SecretGuardMut<'a, S>
-where
-    S: Zeroize,
-{
-    data: &'a mut S,
-}
+/// Move decoded key material, e.g. from base64 or hex, into a locked array. The vector is
+/// zeroized whether or not it has exactly `N` elements.
+impl<T: DefaultIsZeroes, const N: usize> TryFrom<Vec<T>> for SecretArray<T, N> {
+    type Error = LenError;
 
-impl<S: Display + Zeroize> Display for SecretGuardMut<'_, S> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.data)
+    fn try_from(mut vec: Vec<T>) -> Result<Self, Self::Error> {
+        let secret_array = Self::from_slice(&vec);
+        vec.zeroize();
+        secret_array
     }
 }
 
-impl<S> Deref for SecretGuardMut<'_, S>
-where
-    S: Zeroize,
-{
-    type Target = S;
+/// Convenient type alias for secrets whose length is only known at runtime
+///
+/// Unlike a [`SecretVec`], the elements are stored in the boxed slice itself, so exactly the
+/// elements are locked without a `Vec` header in between. The length can't change afterwards.
+pub type SecretSlice<T> = SecretBox<[T]>;
 
-    fn deref(&self) -> &Self::Target {
-        self.data
+impl<T: DefaultIsZeroes> SecretSlice<T> {
+    /// Generate a SecretSlice from a boxed slice
+    ///
+    /// Note that `Vec::into_boxed_slice` reallocates a vector with spare capacity, leaving an
+    /// unzeroized copy of its elements behind.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret's memory can't be locked. See [`SecretBox::try_new`] for a
+    /// non-panicking alternative.
+    pub fn from_boxed_slice(boxed_slice: Box<[T]>) -> Self {
+        SecretBox::new(boxed_slice)
     }
 }
 
-impl<S> DerefMut for SecretGuardMut<'_, S>
-where
-    S: Zeroize,
-{
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        self.data
+impl<S: Zeroize + ?Sized> Drop for SecretBox<S> {
+    fn drop(&mut self) {
+        unsafe { self.release(report_drop_error) }
     }
 }
 
-impl<'a, S: Zeroize> SecretGuard<'a, S> {
-    /// Create a new SecretGuard instance.
-    pub fn new(data: &'a S) -> Self {
-        Self { data }
+impl<S: Zeroize + ?Sized> SecretBox<S> {
+    /// Zeroize the secret, unlock its memory and free it, passing errors to `report`.
+    ///
+    /// The steps run in a fixed order: the memory is unlocked, the secret zeroized, `S`'s own
+    /// `Drop` runs exactly once on the zeroized value and only then is the memory freed or,
+    /// after zeroizing every page of it, unmapped. A handle type can thus still release its
+    /// resource in `Drop`, and it never sees freed memory.
+    ///
+    /// # Safety
+    ///
+    /// Must be called at most once and the box must not be used afterwards.
+    unsafe fn release(&mut self, mut report: impl FnMut(&SecretBoxError)) {
+        #[cfg(all(unix, feature = "atfork"))]
+        atfork::unregister(self.inner_secret.cast());
+
+        if let Some(protection) = &self.idle_protection {
+            if let Err(err) = protection.expose(Access::ReadWrite) {
+                // Zeroizing inaccessible pages would fault, unmapping them discards the secret
+                report(&err);
+                if let Backing::Pages { start, len, .. } = self.backing {
+                    if let Err(err) = memory::unmap_pages(start, len) {
+                        report(&err);
+                        // The secret is neither zeroized nor discarded
+                        #[cfg(feature = "fail-hard")]
+                        fail(err);
+                    }
+                }
+                return;
+            }
+        }
+
+        // Failing to unlock must not take the program down, the secret is zeroized either way.
+        for region in [Some(self.region), self.heap_region].into_iter().flatten() {
+            for err in unlock_memory(region) {
+                report(&err);
+            }
+        }
+
+        if let Some((pattern, overwrite)) = self.drop_pattern {
+            overwrite(self.inner_secret.cast(), pattern);
+        }
+        self.inner_secret.as_mut().zeroize();
+        #[cfg(test)]
+        self.record_zeroized();
+
+        match self.backing {
+            Backing::Heap => drop(Box::from_raw(self.inner_secret.as_ptr())),
+            Backing::Pages { start, len, .. } => {
+                ptr::drop_in_place(self.inner_secret.as_ptr());
+                self.zeroize_pages();
+                if let Err(err) = memory::unmap_pages(start, len) {
+                    report(&err);
+                }
+            }
+            Backing::Aligned { layout } => {
+                ptr::drop_in_place(self.inner_secret.as_ptr());
+                self.zeroize_pages();
+                alloc::dealloc(self.inner_secret.as_ptr().cast(), layout);
+            }
+            Backing::External => ptr::drop_in_place(self.inner_secret.as_ptr()),
+        }
+    }
+
+    /// Zeroize all pages dedicated to a [`Backing::Pages`] or [`Backing::Aligned`] secret,
+    /// including the slack around the secret itself that `Zeroize` doesn't reach.
+    ///
+    /// The secret must have been dropped already and its pages must be writable.
+    unsafe fn zeroize_pages(&self) {
+        std::slice::from_raw_parts_mut(self.region.start as *mut u8, self.region.len).zeroize();
     }
 }
 
-impl<'a, S: Zeroize> SecretGuardMut<'a, S> {
-    /// Create a new SecretGuard instance.
-    pub fn new(data: &'a mut S) -> Self {
-        Self { data }
+impl<S: Zeroize + ?Sized> ZeroizeOnDrop for SecretBox<S> {}
+
+impl<S: Zeroize + ?Sized> From<Box<S>> for SecretBox<S> {
+    /// Same as [`SecretBox::new`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret's memory can't be locked, even though a `.into()` doesn't look
+    /// like it could fail. Use [`SecretBox::try_new`] to handle the error, e.g. with `?`:
+    ///
+    /// ```
+    /// use shush_rs::{SecretBox, SecretBoxError};
+    ///
+    /// fn lock_key(key: Box<[u8; 32]>) -> Result<SecretBox<[u8; 32]>, SecretBoxError> {
+    ///     let key = SecretBox::try_new(key)?;
+    ///     Ok(key)
+    /// }
+    /// # lock_key(Box::new([1; 32])).unwrap();
+    /// ```
+    ///
+    /// This `From` impl gives `SecretBox` the standard library's blanket `TryFrom<Box<S>>`
+    /// with an `Infallible` error, so there is no `TryFrom` impl returning
+    /// [`SecretBoxError`].
+    fn from(source: Box<S>) -> Self {
+        Self::new(source)
     }
 }
 
-/// Marker trait for secrets which are allowed to be cloned
-pub trait CloneableSecret: Clone + Zeroize {}
+impl<S: Zeroize + ?Sized> SecretBox<S> {
+    /// Create a secret value using a pre-boxed value.
+    ///
+    /// On Android and iOS, where apps usually lack the privileges or limits to lock memory,
+    /// locking is always best effort like with [`Self::new_best_effort`], failures are only
+    /// reported to the [`Observer`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret's memory can't be locked. See [`Self::try_new`] for a
+    /// non-panicking alternative.
+    pub fn new(boxed_secret: Box<S>) -> Self {
+        Self::try_new(boxed_secret).unwrap_or_else(|err| fail(err))
+    }
 
-impl CloneableSecret for String {}
+    /// Create a secret value using a pre-boxed value, returning an error if the
+    /// secret's memory can't be locked.
+    ///
+    /// The secret is zeroized before the error is returned.
+    pub fn try_new(boxed_secret: Box<S>) -> Result<Self, SecretBoxError> {
+        Self::try_new_with_policy(boxed_secret, SecretBoxBuilder::new())
+    }
 
-/// Create a SecretGuard that holds a reference to the secret
-pub trait ExposeSecret<S: Zeroize> {
-    /// Expose secret as non-mutable.
-    fn expose_secret(&self) -> SecretGuard<'_, S>;
+    /// Create a secret value using a pre-boxed value, falling back to an unlocked secret
+    /// if the locked-memory limit (`RLIMIT_MEMLOCK`) or the [`set_memlock_budget`] budget
+    /// doesn't allow locking it.
+    ///
+    /// The returned `bool` tells whether the secret's memory is actually locked. An unlocked
+    /// secret is still excluded from core dumps where supported and zeroized on drop.
+    ///
+    /// # Panics
+    ///
+    /// Panics if protecting the memory fails for any other reason.
+    pub fn new_best_effort(boxed_secret: Box<S>) -> (Self, bool) {
+        let policy = SecretBoxBuilder {
+            lock: LockMode::BestEffort,
+            ..SecretBoxBuilder::new()
+        };
+        let secret =
+            Self::try_new_with_policy(boxed_secret, policy).unwrap_or_else(|err| fail(err));
+        let locked = secret.region.locked;
+        (secret, locked)
+    }
 
-    /// Expose secret as mutable.
-    fn expose_secret_mut(&mut self) -> SecretGuardMut<'_, S>;
+    /// Create a secret value using a pre-boxed value without locking its memory.
+    ///
+    /// For environments that forbid `mlock`, e.g. seccomp sandboxes or Android without
+    /// `CAP_IPC_LOCK`. The secret is still excluded from core dumps where supported and
+    /// zeroized on drop. The `no-mlock` feature makes every constructor behave this way.
+    ///
+    /// # Panics
+    ///
+    /// Panics if excluding the memory from core dumps fails.
+    pub fn new_unlocked(boxed_secret: Box<S>) -> Self {
+        Self::try_new_with_policy(boxed_secret, SecretBoxBuilder::new().mlock(false))
+            .unwrap_or_else(|err| fail(err))
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+impl<S: Zeroize> SecretBox<S> {
+    /// Create a secret value on locked pages of its own.
+    ///
+    /// `mlock` and `MADV_DONTDUMP` work on whole pages, so a secret sharing its page with
+    /// other heap allocations also pins and hides those from core dumps. This moves the
+    /// secret to freshly mapped pages instead, so the locked region holds nothing but the
+    /// secret.
+    ///
+    /// Only the memory of `S` itself is moved, heap buffers owned by it are not. Since the
+    /// secret occupies at least a whole page, this is best suited for small, fixed-size
+    /// secrets like keys. On drop the pages are zeroized in full, slack included, before they
+    /// are unmapped.
+    ///
+    /// On platforms without memory protection, like `wasm32-unknown-unknown`, this is the same
+    /// as [`Self::new`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret's memory can't be mapped or locked. See
+    /// [`Self::try_new_locked_pages`] for a non-panicking alternative.
+    pub fn new_locked_pages(boxed_secret: Box<S>) -> Self {
+        Self::try_new_locked_pages(boxed_secret).unwrap_or_else(|err| fail(err))
+    }
 
-    #[derive(Debug, Clone, Default)]
-    struct TestSecret {
-        data: Vec<u8>,
+    /// Same as [`Self::new_locked_pages`], but returns an error if the secret's memory can't
+    /// be mapped or locked.
+    ///
+    /// The secret is zeroized before the error is returned.
+    pub fn try_new_locked_pages(boxed_secret: Box<S>) -> Result<Self, SecretBoxError> {
+        Self::try_new_on_pages(boxed_secret, SecretBoxBuilder::new())
     }
 
-    impl TestSecret {
-        fn new(size: usize) -> Self {
-            let mut data = vec![0; size];
-            data[0] = 1;
-            Self { data }
-        }
+    /// Create a secret value on locked pages of its own, taken from the global allocator.
+    ///
+    /// Like [`Self::new_locked_pages`], the locked region holds nothing but the secret, but
+    /// the pages come from a page-aligned allocation whose size is a multiple of the page
+    /// size instead of a fresh `mmap`. For small secrets that's usually cheaper, since the
+    /// allocator can serve it without a syscall. On drop the pages are zeroized in full before
+    /// they are freed.
+    ///
+    /// There are no guard pages and the pages can't be made inaccessible while idle, use
+    /// [`Self::new_guarded`] or [`Self::new_protected`] for that.
+    ///
+    /// On platforms without memory protection, like `wasm32-unknown-unknown`, this is the same
+    /// as [`Self::new`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret's memory can't be locked or its type needs a larger alignment than
+    /// a page offers. See [`Self::try_new_page_aligned`] for a non-panicking alternative.
+    pub fn new_page_aligned(boxed_secret: Box<S>) -> Self {
+        Self::try_new_page_aligned(boxed_secret).unwrap_or_else(|err| fail(err))
+    }
 
-        fn check_non_zero(&self) -> bool {
-            self.data.iter().any(|&x| x != 0)
+    /// Same as [`Self::new_page_aligned`], but returns an error if the secret's memory can't
+    /// be locked or its type needs a larger alignment than a page offers.
+    ///
+    /// The secret is zeroized before the error is returned.
+    pub fn try_new_page_aligned(mut boxed_secret: Box<S>) -> Result<Self, SecretBoxError> {
+        let policy = SecretBoxBuilder::new();
+        if cfg!(not(any(unix, windows))) {
+            return Self::try_new_with_policy(boxed_secret, policy);
         }
 
-        fn check_zero(&self) -> bool {
-            self.data.iter().all(|&x| x == 0)
+        let page_size = match secret_page_size::<S>() {
+            Ok(page_size) => page_size,
+            Err(err) => {
+                boxed_secret.zeroize();
+                return Err(err);
+            }
+        };
+
+        let len = size_of::<S>().max(1).div_ceil(page_size) * page_size;
+        let layout = match Layout::from_size_align(len, page_size) {
+            Ok(layout) => layout,
+            Err(_) => {
+                boxed_secret.zeroize();
+                return Err(SecretBoxError::RegionOverflow);
+            }
+        };
+        let start = unsafe { alloc::alloc(layout) };
+        if start.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+        let inner_secret = unsafe { move_secret(boxed_secret, start.cast()) };
+
+        let mut secret = Self {
+            inner_secret,
+            backing: Backing::Aligned { layout },
+            region: Region {
+                start: start as usize,
+                len,
+                locked: false,
+                dump_excluded: false,
+            },
+            heap_region: None,
+            idle_protection: None,
+            debug_label: None,
+            region_of: None,
+            policy,
+            drop_pattern: None,
+        };
+        secret.region = unsafe { lock_memory(start, len, policy)? };
+
+        Ok(secret)
+    }
+
+    /// Create a secret value on locked pages of its own, surrounded by inaccessible guard
+    /// pages.
+    ///
+    /// Like [`Self::new_locked_pages`], but the pages right before and after the secret are
+    /// `PROT_NONE` (`PAGE_NOACCESS` on Windows). The secret is placed at the very end of its
+    /// pages, so reading even one byte past it crashes the program instead of disclosing
+    /// adjacent memory, which makes out-of-bounds bugs near the secret easy to spot.
+    ///
+    /// On platforms without memory protection, like `wasm32-unknown-unknown`, this is the same
+    /// as [`Self::new`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret's memory can't be mapped, locked or protected. See
+    /// [`Self::try_new_guarded`] for a non-panicking alternative.
+    pub fn new_guarded(boxed_secret: Box<S>) -> Self {
+        Self::try_new_guarded(boxed_secret).unwrap_or_else(|err| fail(err))
+    }
+
+    /// Same as [`Self::new_guarded`], but returns an error if the secret's memory can't be
+    /// mapped, locked or protected.
+    ///
+    /// The secret is zeroized before the error is returned.
+    pub fn try_new_guarded(boxed_secret: Box<S>) -> Result<Self, SecretBoxError> {
+        Self::try_new_on_pages(boxed_secret, SecretBoxBuilder::new().guard_pages(true))
+    }
+
+    /// Create a secret value on pages mapped with `memfd_secret(2)`, only available on Linux
+    /// with the `memfd-secret` feature.
+    ///
+    /// Since Linux 5.14 such pages are removed from the kernel's direct map, so not even the
+    /// kernel, let alone other processes or a debugger, can read them, which is stronger than
+    /// locking alone. They're also locked and excluded from core dumps like
+    /// [`Self::new_locked_pages`] secrets and count against `RLIMIT_MEMLOCK`, but the kernel
+    /// locks them itself, so [`set_memlock_budget`] doesn't apply to them. Where the
+    /// syscall is missing (`ENOSYS`) this falls back to [`Self::new_locked_pages`],
+    /// [`Self::backing_kind`] tells which one was used. Some kernels only offer it when booted
+    /// with `secretmem.enable=1`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret's memory can't be mapped or locked. See
+    /// [`Self::try_new_memfd_secret`] for a non-panicking alternative.
+    #[cfg(all(target_os = "linux", feature = "memfd-secret"))]
+    pub fn new_memfd_secret(boxed_secret: Box<S>) -> Self {
+        Self::try_new_memfd_secret(boxed_secret).unwrap_or_else(|err| fail(err))
+    }
+
+    /// Same as [`Self::new_memfd_secret`], but returns an error if the secret's memory can't
+    /// be mapped or locked.
+    ///
+    /// The secret is zeroized before the error is returned.
+    #[cfg(all(target_os = "linux", feature = "memfd-secret"))]
+    pub fn try_new_memfd_secret(boxed_secret: Box<S>) -> Result<Self, SecretBoxError> {
+        Self::try_new_on_pages(boxed_secret, SecretBoxBuilder::new().memfd_secret(true))
+    }
+
+    /// Move the secret to pages of its own and protect them according to `policy`.
+    fn try_new_on_pages(
+        mut boxed_secret: Box<S>,
+        policy: SecretBoxBuilder,
+    ) -> Result<Self, SecretBoxError> {
+        if cfg!(not(any(unix, windows))) {
+            return Self::try_new_with_policy(boxed_secret, policy);
+        }
+        let guard_pages = policy.guard_pages;
+
+        let page_size = match secret_page_size::<S>() {
+            Ok(page_size) => page_size,
+            Err(err) => {
+                boxed_secret.zeroize();
+                return Err(err);
+            }
+        };
+
+        let data_len = size_of::<S>().max(1).div_ceil(page_size) * page_size;
+        let guard_len = if guard_pages { page_size } else { 0 };
+        let len = data_len + 2 * guard_len;
+        let mapped = if policy.memfd_secret {
+            memory::map_secret_pages(len).map(|start| start.map(|start| (start, true)))
+        } else {
+            Ok(None)
+        };
+        let mapped = mapped.and_then(|mapped| match mapped {
+            Some(mapped) => Ok(mapped),
+            None => memory::map_pages(len).map(|start| (start, false)),
+        });
+        let (start, secret_memory) = match mapped {
+            Ok(mapped) => mapped,
+            Err(err) => {
+                boxed_secret.zeroize();
+                return Err(err);
+            }
+        };
+        let data_start = start + guard_len;
+        // Right-align a guarded secret so reading past its end hits the trailing guard page,
+        // the offset keeps it aligned since the size of `S` is a multiple of its alignment
+        let offset = if guard_pages {
+            data_len - size_of::<S>()
+        } else {
+            0
+        };
+        let inner_secret = unsafe { move_secret(boxed_secret, (data_start + offset) as *mut S) };
+
+        let mut secret = Self {
+            inner_secret,
+            backing: Backing::Pages {
+                start,
+                len,
+                secret_memory,
+            },
+            region: Region {
+                start: data_start,
+                len: data_len,
+                locked: false,
+                dump_excluded: false,
+            },
+            heap_region: None,
+            idle_protection: None,
+            debug_label: None,
+            region_of: None,
+            policy,
+            drop_pattern: None,
+        };
+        if guard_pages {
+            unsafe {
+                memory::protect_memory(start, guard_len, Access::None)?;
+                memory::protect_memory(data_start + data_len, guard_len, Access::None)?;
+            }
+        }
+        // `memfd_secret` pages are locked by the kernel already, `mlock` rejects them
+        let lock_policy = if secret_memory {
+            policy.mlock(false)
+        } else {
+            policy
+        };
+        secret.region = unsafe { lock_memory(data_start as *const u8, data_len, lock_policy)? };
+        if policy.mprotect_idle {
+            unsafe { memory::protect_memory(data_start, data_len, Access::None)? };
+            secret.idle_protection = Some(IdleProtection {
+                start: data_start,
+                len: data_len,
+                exposed: Mutex::new(0),
+            });
+        }
+
+        Ok(secret)
+    }
+
+    /// Adopt a secret living in memory the caller allocated, e.g. in their own pool of locked
+    /// pages.
+    ///
+    /// The box zeroizes and drops the secret in place but never frees its memory. Beyond
+    /// that, if `already_locked` is `true` the memory is left alone: it's neither locked nor
+    /// unlocked and its core dump setting is kept. If it's `false`, the pages spanning the
+    /// secret are locked like with [`Self::new`] and unlocked on drop.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to an initialized, properly aligned `S` that is valid for reads and
+    /// writes until the box is dropped or [`Self::into_inner`] is called, and nothing else may
+    /// access it in the meantime.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `already_locked` is `false` and the secret's memory can't be locked.
+    pub unsafe fn from_locked_ptr(ptr: NonNull<S>, already_locked: bool) -> Self {
+        let region = if already_locked {
+            Region {
+                start: ptr.as_ptr() as usize,
+                len: 0,
+                locked: false,
+                dump_excluded: false,
+            }
+        } else {
+            match lock_memory(ptr.as_ptr().cast(), size_of::<S>(), SecretBoxBuilder::new()) {
+                Ok(region) => region,
+                Err(err) => fail(err),
+            }
+        };
+
+        Self {
+            inner_secret: ptr,
+            backing: Backing::External,
+            region,
+            heap_region: None,
+            idle_protection: None,
+            debug_label: None,
+            region_of: None,
+            policy: SecretBoxBuilder::new(),
+            drop_pattern: None,
+        }
+    }
+
+    /// Create a secret value whose memory is inaccessible while it isn't exposed.
+    ///
+    /// The secret is moved to pages of its own like with [`Self::new_locked_pages`], which
+    /// are locked and protected with `mprotect` (`VirtualProtect` on Windows). They are only
+    /// made readable while a [`SecretGuard`] or writable while a [`SecretGuardMut`] is alive,
+    /// any other access to them crashes the program instead of silently reading the secret.
+    ///
+    /// On platforms without memory protection, like `wasm32-unknown-unknown`, this is the same
+    /// as [`Self::new`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret's memory can't be mapped, locked or protected. See
+    /// [`Self::try_new_protected`] for a non-panicking alternative.
+    pub fn new_protected(boxed_secret: Box<S>) -> Self {
+        Self::try_new_protected(boxed_secret).unwrap_or_else(|err| fail(err))
+    }
+
+    /// Same as [`Self::new_protected`], but returns an error if the secret's memory can't be
+    /// mapped, locked or protected.
+    ///
+    /// The secret is zeroized before the error is returned.
+    pub fn try_new_protected(boxed_secret: Box<S>) -> Result<Self, SecretBoxError> {
+        Self::try_new_on_pages(boxed_secret, SecretBoxBuilder::new().mprotect_idle(true))
+    }
+
+    /// Zeroize the secret in every child process forked from now on, e.g. by a server that
+    /// daemonizes after loading its keys.
+    ///
+    /// A `pthread_atfork` handler zeroizes the child's copy of the secret right after
+    /// `fork`, the parent's secret is left untouched. The registration ends when the secret
+    /// is dropped. `posix_spawn` and `vfork` don't run `fork` handlers, but the child
+    /// replaces its memory with a new program anyway.
+    ///
+    /// `S`'s `Zeroize` impl runs in the child while it's still single-threaded, it must not
+    /// allocate or take locks. The impls of the `zeroize` crate do neither.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `fork` handlers can't be installed.
+    #[cfg(all(unix, feature = "atfork"))]
+    pub fn register_atfork_zeroize(&self) {
+        if size_of::<S>() == 0 {
+            return;
+        }
+        let protected = self
+            .idle_protection
+            .as_ref()
+            .map(|protection| (protection.start, protection.len));
+        atfork::register(self.inner_secret.cast(), zeroize_erased::<S>, protected);
+    }
+
+    /// Move the secret out of the box without zeroizing it, e.g. to pass it to an API that
+    /// takes ownership of it.
+    ///
+    /// The secret's memory is unlocked and included in core dumps again, a
+    /// [`Self::new_protected`] secret is moved to a regular heap allocation. From here on the
+    /// caller is responsible for the secret's lifetime and for zeroizing it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pages of a [`Self::new_protected`] secret can't be made accessible.
+    pub fn into_inner(self) -> Box<S> {
+        let this = ManuallyDrop::new(self);
+        #[cfg(all(unix, feature = "atfork"))]
+        atfork::unregister(this.inner_secret.cast());
+        if let Some(protection) = &this.idle_protection {
+            protection
+                .expose(Access::ReadWrite)
+                .unwrap_or_else(|err| fail(err));
+        }
+
+        for region in [Some(this.region), this.heap_region].into_iter().flatten() {
+            for err in unsafe { unlock_memory(region) } {
+                report_drop_error(&err);
+            }
+        }
+
+        match this.backing {
+            Backing::Heap => unsafe { Box::from_raw(this.inner_secret.as_ptr()) },
+            Backing::Pages { start, len, .. } => unsafe {
+                let mut boxed_secret = Box::<S>::new_uninit();
+                move_value(this.inner_secret.as_ptr(), boxed_secret.as_mut_ptr());
+                if let Err(err) = memory::unmap_pages(start, len) {
+                    report_drop_error(&err);
+                }
+                boxed_secret.assume_init()
+            },
+            Backing::Aligned { layout } => unsafe {
+                let mut boxed_secret = Box::<S>::new_uninit();
+                move_value(this.inner_secret.as_ptr(), boxed_secret.as_mut_ptr());
+                alloc::dealloc(this.inner_secret.as_ptr().cast(), layout);
+                boxed_secret.assume_init()
+            },
+            Backing::External => unsafe {
+                let mut boxed_secret = Box::<S>::new_uninit();
+                move_value(this.inner_secret.as_ptr(), boxed_secret.as_mut_ptr());
+                boxed_secret.assume_init()
+            },
+        }
+    }
+
+    /// Leak the secret for the rest of the program, e.g. for a process-wide master key that
+    /// would otherwise be threaded through everything. Like [`Box::leak`], but the secret
+    /// stays locked and excluded from core dumps.
+    ///
+    /// The secret is never zeroized: it stays resident until the process exits. A
+    /// [`Self::new_protected`] secret is left accessible for good, and a secret adopted with
+    /// [`Self::from_locked_ptr`] is first moved to memory of its own, since the caller's
+    /// memory only outlives the box.
+    ///
+    /// ```
+    /// use shush_rs::SecretBox;
+    ///
+    /// let master_key: &'static [u8; 32] = SecretBox::new(Box::new([7u8; 32])).leak();
+    /// assert_eq!(master_key[0], 7);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pages of a [`Self::new_protected`] secret can't be made accessible.
+    pub fn leak(self) -> &'static S
+    where
+        S: 'static,
+    {
+        self.leak_mut()
+    }
+
+    /// Same as [`Self::leak`], but the leaked secret can still be modified.
+    pub fn leak_mut(self) -> &'static mut S
+    where
+        S: 'static,
+    {
+        if let Backing::External = self.backing {
+            let policy = self.policy;
+            return policy.build(self.into_inner()).leak_mut();
+        }
+
+        let mut this = ManuallyDrop::new(self);
+        if let Some(protection) = &this.idle_protection {
+            protection
+                .expose(Access::ReadWrite)
+                .unwrap_or_else(|err| fail(err));
+        }
+        // SAFETY: the secret's memory is never unlocked, unmapped or freed without `Drop`
+        unsafe { this.inner_secret.as_mut() }
+    }
+
+    /// Overwrite the secret as `pattern` asks whenever it's zeroized on drop, see
+    /// [`Self::zeroize_with`]. Clones and [`Self::replace`] keep the pattern.
+    ///
+    /// ```
+    /// use shush_rs::{SecretBox, ZeroizePattern};
+    ///
+    /// let key = SecretBox::new(Box::new([1u8; 32])).with_zeroize_pattern(ZeroizePattern::Ones);
+    /// drop(key);
+    /// ```
+    pub fn with_zeroize_pattern(mut self, pattern: ZeroizePattern) -> Self
+    where
+        S: AsMut<[u8]>,
+    {
+        self.drop_pattern = Some((pattern, pattern::overwrite_erased::<S>));
+        self
+    }
+}
+
+impl<S: Zeroize + ?Sized> SecretBox<S> {
+    /// Zeroize the secret and unlock its memory right away, e.g. as soon as a key has been
+    /// used, instead of waiting for the end of the scope.
+    ///
+    /// This does the same as dropping the box, but returns the first error instead of passing
+    /// it to the hook set with [`set_drop_error_hook`]. The secret is zeroized and freed even
+    /// if unlocking fails. The one exception is a [`Self::new_protected`] secret whose pages
+    /// can't be made accessible again: those are unmapped without zeroizing them.
+    ///
+    /// ```
+    /// use shush_rs::SecretBox;
+    ///
+    /// let key = SecretBox::new(Box::new([1u8; 32]));
+    /// // ... use the key ...
+    /// key.destroy().expect("key's memory is unlocked");
+    /// ```
+    pub fn destroy(self) -> Result<(), SecretBoxError> {
+        let mut this = ManuallyDrop::new(self);
+        let mut result = Ok(());
+        unsafe {
+            this.release(|err| {
+                if result.is_ok() {
+                    result = Err(*err);
+                }
+            })
+        };
+        result
+    }
+
+    /// Zeroize the secret, read its data back to confirm every byte is zero and drop it, for
+    /// builds that must prove the scrub happened rather than trust the compiler kept it.
+    ///
+    /// The data checked is [`LockableRegion::region`], e.g. a `Vec`'s whole buffer including
+    /// spare capacity. The box is dropped either way, errors from unlocking its memory go to
+    /// the hook set with [`set_drop_error_hook`].
+    ///
+    /// ```
+    /// use shush_rs::SecretBox;
+    ///
+    /// let key = SecretBox::new(Box::new([1u8; 32]));
+    /// key.drop_verified().expect("key is zeroized");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns the offset of the first byte that is still set after zeroizing.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret can't be made accessible, see [`ExposeSecret::expose_secret_mut`].
+    pub fn drop_verified(mut self) -> Result<(), ZeroizeVerifyError>
+    where
+        S: LockableRegion,
+    {
+        let mut secret = self.expose_secret_mut();
+        (*secret).zeroize();
+        let (ptr, len) = secret.region();
+        unsafe { verify_zeroed(ptr, len) }
+    }
+
+    /// Derive a new secret from this one, e.g. expanding a master key into a subkey.
+    ///
+    /// `f` runs on the exposed secret and its result is moved into a fresh locked
+    /// `SecretBox`. This secret is zeroized afterwards. The derived value briefly lives on
+    /// the stack before it's boxed, use [`SecretBox::new_with_mut`] to derive straight into
+    /// locked memory instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the derived secret's memory can't be locked.
+    pub fn map<T: Zeroize>(self, f: impl FnOnce(&S) -> T) -> SecretBox<T> {
+        let derived = f(&self.expose_secret());
+        SecretBox::new(Box::new(derived))
+    }
+
+    /// Tag the secret with a label printed by its `Debug` impl, e.g. to tell apart secrets
+    /// in logs. The secret itself stays redacted.
+    ///
+    /// ```
+    /// use shush_rs::SecretBox;
+    ///
+    /// let secret = SecretBox::new(Box::new([0u8; 32])).with_debug_label("signing-key");
+    /// assert_eq!(format!("{:?}", secret), "SecretBox<[u8; 32]>(signing-key: [REDACTED])");
+    /// ```
+    pub fn with_debug_label(mut self, label: &'static str) -> Self {
+        self.debug_label = Some(label);
+        self
+    }
+
+    /// Overwrite the secret's bytes as `pattern` asks and zeroize it, e.g. where a
+    /// certification mandates more than a single pass of zeros.
+    ///
+    /// ```
+    /// use shush_rs::{ExposeSecret, SecretBox, ZeroizePattern};
+    ///
+    /// let mut key = SecretBox::new(Box::new([1u8; 32]));
+    /// key.zeroize_with(ZeroizePattern::MultiPass);
+    /// assert_eq!(*key.expose_secret(), [0; 32]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret can't be made accessible, see [`ExposeSecret::expose_secret_mut`].
+    pub fn zeroize_with(&mut self, pattern: ZeroizePattern)
+    where
+        S: AsMut<[u8]>,
+    {
+        let mut secret = self.expose_secret_mut();
+        pattern::overwrite((*secret).as_mut(), pattern);
+        secret.zeroize();
+    }
+
+    /// Pass a pointer to the secret's bytes and their length to `f`, e.g. for a C library to
+    /// generate a key straight into the locked buffer.
+    ///
+    /// The secret is exposed mutably for the duration of `f`, so idle-protected pages are
+    /// writable meanwhile. For a `Vec<u8>` the length is [`Vec::len`], not its capacity:
+    /// size the vector before handing it out, e.g. with `vec![0; 32]`.
+    ///
+    /// ```
+    /// use shush_rs::{ExposeSecret, SecretArray};
+    ///
+    /// unsafe extern "C" fn fill(buf: *mut u8, len: usize) -> i32 {
+    ///     buf.write_bytes(7, len);
+    ///     1
+    /// }
+    ///
+    /// let mut key = SecretArray::<u8, 32>::zeroed();
+    /// let result = unsafe { key.with_mut_ptr(|ptr, len| fill(ptr, len)) };
+    /// assert_eq!(result, 1);
+    /// assert_eq!(*key.expose_secret(), [7; 32]);
+    /// ```
+    ///
+    /// # Safety
+    ///
+    /// `f` must not access memory outside of the `len` bytes starting at the pointer, nor
+    /// use the pointer once it has returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret can't be made accessible, see [`ExposeSecret::expose_secret_mut`].
+    pub unsafe fn with_mut_ptr<R>(&mut self, f: impl FnOnce(*mut u8, usize) -> R) -> R
+    where
+        S: AsMut<[u8]>,
+    {
+        let mut secret = self.expose_secret_mut();
+        let bytes = (*secret).as_mut();
+        f(bytes.as_mut_ptr(), bytes.len())
+    }
+
+    /// Kind of memory holding the secret, e.g. to tell whether
+    /// `SecretBox::new_memfd_secret` got `memfd_secret(2)` pages or fell back to locked ones.
+    ///
+    /// ```
+    /// use shush_rs::{BackingKind, SecretBox};
+    ///
+    /// let key = SecretBox::new(Box::new([1u8; 32]));
+    /// assert_eq!(key.backing_kind(), BackingKind::Heap);
+    /// ```
+    pub fn backing_kind(&self) -> BackingKind {
+        match self.backing {
+            Backing::Heap => BackingKind::Heap,
+            Backing::Pages {
+                secret_memory: true,
+                ..
+            } => BackingKind::MemfdSecret,
+            Backing::Pages { .. } => BackingKind::Pages,
+            Backing::Aligned { .. } => BackingKind::PageAligned,
+            Backing::External => BackingKind::External,
+        }
+    }
+
+    /// Number of bytes locked for this secret, e.g. to sum up how close a process is to its
+    /// `RLIMIT_MEMLOCK`.
+    ///
+    /// Locking works on whole pages, so this is the page-aligned length of the secret's pages
+    /// plus, for secrets built with [`Self::new_with_region`], of its heap data. It can be much
+    /// larger than the secret itself: a 32-byte key usually costs a 4 KiB page. Pages that
+    /// aren't locked, e.g. for [`Self::new_unlocked`] secrets, don't count. A page shared by
+    /// the secret and its heap data counts twice, making this an upper bound. On Windows this
+    /// is the length passed to `VirtualLock`, which rounds it up to whole pages itself.
+    ///
+    /// ```
+    /// use shush_rs::SecretBox;
+    ///
+    /// let key = SecretBox::new(Box::new([0u8; 32]));
+    /// # if cfg!(all(unix, not(feature = "no-mlock"))) {
+    /// assert!(key.locked_bytes() >= 32);
+    /// # }
+    /// ```
+    pub fn locked_bytes(&self) -> usize {
+        [Some(self.region), self.heap_region]
+            .into_iter()
+            .flatten()
+            .filter(|region| region.locked)
+            .map(|region| region.len)
+            .sum()
+    }
+
+    /// Ask the kernel whether the secret's pages are resident and locked into memory, e.g. to
+    /// assert in tests that the crate does what it claims.
+    ///
+    /// This checks the pages holding the secret itself and, for secrets built with
+    /// [`Self::new_with_region`], the pages of its heap data. Returns `Ok(false)` if any of
+    /// them is swapped out or isn't locked, which is expected for [`Self::new_unlocked`]
+    /// secrets unless another secret shares their pages.
+    ///
+    /// Only available on Linux, where it reads `/proc/self/smaps` and calls `mincore`. Errors
+    /// come from those two.
+    ///
+    /// ```
+    /// use shush_rs::SecretBox;
+    ///
+    /// let secret = SecretBox::new(Box::new([0u8; 32]));
+    /// # if cfg!(not(feature = "no-mlock")) {
+    /// assert!(secret.is_locked().unwrap());
+    /// # }
+    /// ```
+    #[cfg(target_os = "linux")]
+    pub fn is_locked(&self) -> std::io::Result<bool> {
+        let regions = [Some(self.region), self.heap_region];
+        for region in regions.into_iter().flatten() {
+            if !memory::is_range_locked(region.start as *const u8, region.len)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Check that the secret's pages are resident and locked into memory, e.g. to assert in CI
+    /// that a secret can't have been written to swap.
+    ///
+    /// Same as [`Self::is_locked`], but the error tells which page failed the check and why.
+    ///
+    /// ```
+    /// use shush_rs::SecretBox;
+    ///
+    /// let secret = SecretBox::new(Box::new([0u8; 32]));
+    /// # if cfg!(not(feature = "no-mlock")) {
+    /// secret.assert_resident().unwrap();
+    /// # }
+    /// ```
+    #[cfg(target_os = "linux")]
+    pub fn assert_resident(&self) -> Result<(), ResidencyError> {
+        let regions = [Some(self.region), self.heap_region];
+        for region in regions.into_iter().flatten() {
+            memory::check_range_locked(region.start as *const u8, region.len)?;
+        }
+        Ok(())
+    }
+
+    /// Expose the secret behind a [`Pin`], making explicit that its address doesn't change,
+    /// e.g. when passing it to FFI that records the pointer.
+    ///
+    /// The secret stays at the same address for as long as this box owns it, see
+    /// [`SecretBox`]. It requires `S: Unpin`, since [`ExposeSecret::expose_secret_mut`] and
+    /// [`Self::into_inner`] can still move the value itself.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret can't be made accessible, see [`ExposeSecret::expose_secret`].
+    pub fn as_pinned_ref(&self) -> Pin<SecretGuard<'_, S>>
+    where
+        S: Unpin,
+    {
+        Pin::new(self.expose_secret())
+    }
+
+    /// Expose the secret only for the duration of `f`.
+    ///
+    /// Unlike a free-floating [`SecretGuard`], the secret can't stay exposed by accident, a
+    /// [`Self::new_protected`] secret is made inaccessible again as soon as `f` returns (or
+    /// unwinds).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret can't be made accessible, see [`ExposeSecret::expose_secret`].
+    pub fn with_exposed<R>(&self, f: impl FnOnce(&S) -> R) -> R {
+        f(&self.expose_secret())
+    }
+
+    /// Expose the secret mutably only for the duration of `f`, see [`Self::with_exposed`].
+    pub fn with_exposed_mut<R>(&mut self, f: impl FnOnce(&mut S) -> R) -> R {
+        f(&mut self.expose_secret_mut())
+    }
+
+    /// Expose only the bytes in `range` for the duration of `f`, e.g. to split combined key
+    /// material into a key and an IV.
+    ///
+    /// Secrets deliberately implement no `Index`, an indexed byte is too easily copied out.
+    ///
+    /// ```
+    /// use shush_rs::SecretBox;
+    ///
+    /// let key_material = SecretBox::new(Box::new([7u8; 48]));
+    /// let iv_len = key_material.with_range(32..48, |iv| iv.len());
+    /// assert_eq!(iv_len, 16);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds like slicing does, or if the secret can't be made
+    /// accessible, see [`ExposeSecret::expose_secret`].
+    pub fn with_range<R>(&self, range: Range<usize>, f: impl FnOnce(&[u8]) -> R) -> R
+    where
+        S: AsRef<[u8]>,
+    {
+        f(&(*self.expose_secret()).as_ref()[range])
+    }
+
+    /// Copy the secret's bytes into a `Vec` that is zeroized when dropped, for APIs that
+    /// need an owned buffer.
+    ///
+    /// The copy is **not** locked and not excluded from core dumps, and it's only zeroized if
+    /// whoever ends up owning it drops it. Keep it as short-lived as possible.
+    ///
+    /// ```
+    /// use shush_rs::SecretVec;
+    ///
+    /// let key = SecretVec::from(vec![1u8, 2, 3]);
+    /// let owned = key.expose_owned();
+    /// assert_eq!(*owned, [1, 2, 3]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret can't be made accessible, see [`ExposeSecret::expose_secret`].
+    pub fn expose_owned(&self) -> Zeroizing<Vec<u8>>
+    where
+        S: AsRef<[u8]>,
+    {
+        Zeroizing::new((*self.expose_secret()).as_ref().to_vec())
+    }
+
+    /// Replace the secret with `new`, zeroizing and unlocking the previous one, e.g. to
+    /// rotate a key.
+    ///
+    /// Only the boxes are swapped, both secrets stay in their locked memory and the plaintext
+    /// is never copied. The debug label of this box is kept.
+    pub fn replace(&mut self, mut new: SecretBox<S>) {
+        new.debug_label = self.debug_label;
+        new.drop_pattern = self.drop_pattern;
+        drop(mem::replace(self, new));
+    }
+
+    fn try_new_with_policy(
+        mut boxed_secret: Box<S>,
+        policy: SecretBoxBuilder,
+    ) -> Result<Self, SecretBoxError> {
+        let len = size_of_val(&*boxed_secret);
+        let secret_ptr = boxed_secret.as_ref() as *const S;
+
+        let region = match unsafe { lock_memory(secret_ptr.cast(), len, policy) } {
+            Ok(region) => region,
+            Err(err) => {
+                boxed_secret.zeroize();
+                return Err(err);
+            }
+        };
+
+        Ok(Self {
+            inner_secret: NonNull::from(Box::leak(boxed_secret)),
+            backing: Backing::Heap,
+            region,
+            heap_region: None,
+            idle_protection: None,
+            debug_label: None,
+            region_of: None,
+            policy,
+            drop_pattern: None,
+        })
+    }
+
+    /// Exclude the secret's memory from core dumps again before exposing it.
+    fn reassert_dump_exclusion(&self) -> Result<(), ExposeError> {
+        for region in [Some(self.region), self.heap_region].into_iter().flatten() {
+            unsafe { memory::reassert_dump_exclusion(region) }.map_err(ExposeError::Protection)?;
+        }
+        Ok(())
+    }
+
+    /// Additionally lock the `len` bytes at `ptr` owned by the secret, e.g. the buffer of a
+    /// `String`.
+    fn try_lock_heap(mut self, ptr: *const u8, len: usize) -> Result<Self, SecretBoxError> {
+        if len != 0 {
+            self.heap_region = Some(unsafe { lock_memory(ptr, len, self.policy)? });
+        }
+        Ok(self)
+    }
+}
+
+impl<S: Zeroize + LockableRegion> SecretBox<S> {
+    /// Create a secret value, locking the memory reported by [`LockableRegion::region`] along
+    /// with the value itself.
+    ///
+    /// Unlike [`Self::new`], which only locks the top-level value, this also locks e.g. the
+    /// heap buffer of a `String` or `Vec`. If the buffer is reallocated through
+    /// [`ExposeSecret::expose_secret_mut`], the new buffer is locked and the old one unlocked
+    /// when the guard is dropped. The allocator has already freed the old buffer by then, so
+    /// it can't be zeroized, reserve enough capacity up front to avoid reallocations.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret's memory can't be locked. See [`Self::try_new_with_region`] for a
+    /// non-panicking alternative.
+    pub fn new_with_region(boxed_secret: Box<S>) -> Self {
+        Self::try_new_with_region(boxed_secret).unwrap_or_else(|err| fail(err))
+    }
+
+    /// Same as [`Self::new_with_region`], but returns an error if the secret's memory can't be
+    /// locked.
+    ///
+    /// The secret is zeroized before the error is returned.
+    pub fn try_new_with_region(boxed_secret: Box<S>) -> Result<Self, SecretBoxError> {
+        let mut secret = Self::try_new(boxed_secret)?;
+        secret.region_of = Some(region_of::<S>);
+        let (ptr, len) = unsafe { secret.inner_secret.as_ref() }.region();
+
+        // Inline data like an array's is already locked with the value
+        let start = secret.inner_secret.as_ptr() as usize;
+        let inline = start..=start + size_of::<S>();
+        if inline.contains(&(ptr as usize)) && inline.contains(&(ptr as usize + len)) {
+            return Ok(secret);
+        }
+        secret.try_lock_heap(ptr, len)
+    }
+
+    /// Move the secret's value into a new secret built by `f` without copying its heap data,
+    /// e.g. turning a `String` into its bytes.
+    ///
+    /// The heap data's lock moves to the new secret along with the data, so it stays locked
+    /// throughout instead of being unlocked and locked again.
+    pub(crate) fn convert_with_region<T: Zeroize + LockableRegion>(
+        mut self,
+        f: impl FnOnce(S) -> T,
+    ) -> SecretBox<T>
+    where
+        S: Default,
+    {
+        if let Some(protection) = &self.idle_protection {
+            protection
+                .expose(Access::ReadWrite)
+                .unwrap_or_else(|err| fail(err));
+        }
+        // Not through `expose_secret_mut`, its guard would unlock the buffer once it moves out
+        let value = mem::take(unsafe { self.inner_secret.as_mut() });
+        if let Some(protection) = &self.idle_protection {
+            if let Err(err) = protection.conceal() {
+                report_drop_error(&err);
+            }
+        }
+
+        let mut converted = SecretBox::new(Box::new(f(value)));
+        converted.region_of = Some(region_of::<T>);
+        converted.debug_label = self.debug_label;
+        let (ptr, len) = unsafe { converted.inner_secret.as_ref() }.region();
+        let (start, end) = (ptr as usize, ptr as usize + len);
+        match self.heap_region.take() {
+            Some(heap_region)
+                if heap_region.start <= start && end <= heap_region.start + heap_region.len =>
+            {
+                converted.heap_region = Some(heap_region);
+                converted
+            }
+            heap_region => {
+                // `f` moved the data after all, this secret unlocks the old buffer on drop
+                self.heap_region = heap_region;
+                converted
+                    .try_lock_heap(ptr, len)
+                    .unwrap_or_else(|err| fail(err))
+            }
+        }
+    }
+}
+
+impl<S: DeepZeroize + LockableRegion> SecretBox<S> {
+    /// Create a secret value whose memory is fully covered, see [`DeepZeroize`].
+    ///
+    /// Same as [`Self::new_with_region`], but only accepts types whose `Zeroize` impl scrubs
+    /// all the memory they own, so both locking and zeroizing reach the secret's heap data.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret's memory can't be locked. See [`Self::try_new_deep`] for a
+    /// non-panicking alternative.
+    pub fn new_deep(boxed_secret: Box<S>) -> Self {
+        Self::new_with_region(boxed_secret)
+    }
+
+    /// Same as [`Self::new_deep`], but returns an error if the secret's memory can't be
+    /// locked.
+    pub fn try_new_deep(boxed_secret: Box<S>) -> Result<Self, SecretBoxError> {
+        Self::try_new_with_region(boxed_secret)
+    }
+}
+
+/// Size of the pages to place an `S` on, or an error if `S` needs a larger alignment than a
+/// page offers.
+fn secret_page_size<S>() -> Result<usize, SecretBoxError> {
+    let page_size = memory::page_size()?;
+    if align_of::<S>() > page_size {
+        return Err(SecretBoxError::OverAligned {
+            align: align_of::<S>(),
+            page_size,
+        });
+    }
+    Ok(page_size)
+}
+
+/// Move the secret out of its box into `dst`, zeroizing the memory it leaves behind.
+unsafe fn move_secret<S>(boxed_secret: Box<S>, dst: *mut S) -> NonNull<S> {
+    let src = Box::into_raw(boxed_secret);
+    move_value(src, dst);
+    // `S` has been moved, only free the allocation
+    drop(Box::from_raw(src.cast::<MaybeUninit<S>>()));
+    NonNull::new_unchecked(dst)
+}
+
+/// Check that the `len` bytes at `ptr` are zero, with volatile reads so the check can't be
+/// optimized out any more than the writes it checks.
+unsafe fn verify_zeroed(ptr: *const u8, len: usize) -> Result<(), ZeroizeVerifyError> {
+    match (0..len).find(|&offset| ptr::read_volatile(ptr.add(offset)) != 0) {
+        Some(offset) => Err(ZeroizeVerifyError { offset }),
+        None => Ok(()),
+    }
+}
+
+/// Move the value at `src` to `dst` and zeroize the bytes left behind at `src`.
+///
+/// The value at `src` must not be used or dropped afterwards.
+unsafe fn move_value<S>(src: *mut S, dst: *mut S) {
+    ptr::copy_nonoverlapping(src, dst, 1);
+    std::slice::from_raw_parts_mut(src.cast::<u8>(), size_of::<S>()).zeroize();
+}
+
+impl<S: Zeroize + Default> SecretBox<S> {
+    /// Create a secret value using a function that can initialize the vale in-place.
+    pub fn new_with_mut(ctr: impl FnOnce(&mut S)) -> Self {
+        let mut secret = Self::default();
+        ctr(&mut *secret.expose_secret_mut());
+        secret
+    }
+
+    /// Create a secret holding `S::default()`, returning an error instead of panicking like
+    /// [`Default::default`] if the secret's memory can't be locked.
+    pub fn try_default() -> Result<Self, SecretBoxError> {
+        Self::try_new(Box::<S>::default())
+    }
+}
+
+impl<S: Zeroize> SecretBox<S> {
+    /// Create a secret value using the provided function as a constructor.
+    ///
+    /// The secret's allocation is made and locked before the constructor runs. The constructed
+    /// value is then moved straight into it and the stack copy it leaves behind is zeroized.
+    /// Heap buffers owned by the value, like a `Vec`'s, move along without being copied.
+    ///
+    /// **Note:** using [`Self::new_with_mut`] or [`Self::new_with_uninit`] is preferable when
+    /// possible, since the value briefly lives on the stack, where the compiler is free to
+    /// make further copies.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret's memory can't be locked.
+    pub fn new_with_ctr(ctr: impl FnOnce() -> S) -> Self {
+        match Self::try_new_with_ctr(|| Ok::<_, Infallible>(ctr())) {
+            Ok(secret) => secret,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Same as [`Self::new_with_ctr`], but the constructor can be fallible.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret's memory can't be locked.
+    pub fn try_new_with_ctr<E>(ctr: impl FnOnce() -> Result<S, E>) -> Result<Self, E> {
+        /// Unlocks the slot if `ctr` fails or panics.
+        struct Unwind(Region);
+
+        impl Drop for Unwind {
+            fn drop(&mut self) {
+                for err in unsafe { unlock_memory(self.0) } {
+                    report_drop_error(&err);
+                }
+            }
+        }
+
+        let mut slot = Box::<S>::new_uninit();
+        let region = unsafe {
+            lock_memory(
+                slot.as_ptr().cast(),
+                size_of::<S>(),
+                SecretBoxBuilder::new(),
+            )
+        }
+        .unwrap_or_else(|err| fail(err));
+
+        let unwind = Unwind(region);
+        let mut data = ManuallyDrop::new(ctr()?);
+        mem::forget(unwind);
+        unsafe { move_value(&mut *data, slot.as_mut_ptr()) };
+
+        Ok(Self {
+            inner_secret: NonNull::from(Box::leak(slot)).cast(),
+            backing: Backing::Heap,
+            region,
+            heap_region: None,
+            idle_protection: None,
+            debug_label: None,
+            region_of: None,
+            policy: SecretBoxBuilder::new(),
+            drop_pattern: None,
+        })
+    }
+
+    /// Create a secret value by initializing it in place in its locked allocation, e.g. for
+    /// types without a cheap [`Default`] that a C library or `MaybeUninit::write` fills.
+    ///
+    /// Unlike with [`Self::new_with_ctr`], the value never exists outside the locked memory.
+    /// If `init` panics, whatever it wrote so far is zeroized, but not dropped.
+    ///
+    /// ```
+    /// use shush_rs::{ExposeSecret, SecretBox};
+    ///
+    /// let secret = unsafe {
+    ///     SecretBox::<[u64; 4]>::new_with_uninit(|slot| {
+    ///         slot.write([1, 2, 3, 4]);
+    ///     })
+    /// };
+    /// assert_eq!(*secret.expose_secret(), [1, 2, 3, 4]);
+    /// ```
+    ///
+    /// # Safety
+    ///
+    /// `init` must fully initialize the value before it returns.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret's memory can't be locked.
+    pub unsafe fn new_with_uninit(init: impl FnOnce(&mut MaybeUninit<S>)) -> Self {
+        /// Zeroizes and unlocks the slot if `init` panics.
+        struct Unwind<'a, S> {
+            slot: &'a mut MaybeUninit<S>,
+            region: Region,
+        }
+
+        impl<S> Drop for Unwind<'_, S> {
+            fn drop(&mut self) {
+                unsafe {
+                    std::slice::from_raw_parts_mut(
+                        self.slot.as_mut_ptr().cast::<MaybeUninit<u8>>(),
+                        size_of::<S>(),
+                    )
+                }
+                .zeroize();
+                for err in unsafe { unlock_memory(self.region) } {
+                    report_drop_error(&err);
+                }
+            }
+        }
+
+        let mut slot = Box::<S>::new_uninit();
+        let region = lock_memory(
+            slot.as_ptr().cast(),
+            size_of::<S>(),
+            SecretBoxBuilder::new(),
+        )
+        .unwrap_or_else(|err| fail(err));
+
+        let unwind = Unwind {
+            slot: &mut slot,
+            region,
+        };
+        init(unwind.slot);
+        mem::forget(unwind);
+
+        Self {
+            inner_secret: NonNull::from(Box::leak(slot)).cast(),
+            backing: Backing::Heap,
+            region,
+            heap_region: None,
+            idle_protection: None,
+            debug_label: None,
+            region_of: None,
+            policy: SecretBoxBuilder::new(),
+            drop_pattern: None,
+        }
+    }
+}
+
+/// Panics if the secret's memory can't be locked, see [`SecretBox::try_default`] for a
+/// non-panicking alternative.
+impl<S: Zeroize + Default> Default for SecretBox<S> {
+    fn default() -> Self {
+        Self::try_default().unwrap_or_else(|err| fail(err))
+    }
+}
+
+impl<S: Zeroize + ?Sized> Debug for SecretBox<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretBox<{}>(", any::type_name::<S>())?;
+        if let Some(label) = self.debug_label {
+            write!(f, "{}: ", label)?;
+        }
+        write!(f, "[REDACTED])")
+    }
+}
+
+impl<S: CloneableSecret> SecretBox<S> {
+    /// Overwrite the secret of `dst` with a clone of this one, e.g. to refresh a secret
+    /// without allocating and locking a new box.
+    ///
+    /// `dst`'s secret is zeroized first and then cloned into with [`Clone::clone_from`], which
+    /// reuses its heap buffers where possible. A buffer that has to grow is reallocated only
+    /// after being zeroized, and for secrets built with [`Self::new_with_region`] the new
+    /// buffer is locked. `dst` keeps its own debug label.
+    pub fn clone_into(&self, dst: &mut SecretBox<S>) {
+        let source = self.expose_secret();
+        let mut target = dst.expose_secret_mut();
+        target.zeroize();
+        target.clone_from(&source);
+    }
+
+    /// Same as [`Clone::clone`], but returns an error if the clone's memory can't be locked,
+    /// e.g. because `RLIMIT_MEMLOCK` is exhausted.
+    ///
+    /// The partial clone is zeroized before the error is returned.
+    pub fn try_clone(&self) -> Result<Self, SecretBoxError> {
+        let boxed_secret = Box::new(self.expose_secret().clone());
+        let mut secret = match self.backing {
+            Backing::Pages { .. } => SecretBox::try_new_on_pages(boxed_secret, self.policy),
+            Backing::Aligned { .. } => SecretBox::try_new_page_aligned(boxed_secret),
+            Backing::Heap | Backing::External => {
+                SecretBox::try_new_with_policy(boxed_secret, self.policy)
+            }
+        }?;
+        secret.debug_label = self.debug_label;
+        secret.drop_pattern = self.drop_pattern;
+        // Keep locking the heap data of a `new_with_region` secret
+        if let Some(region_of) = self.region_of {
+            let (ptr, len) = unsafe { region_of(secret.inner_secret.cast()) };
+            secret = secret.try_lock_heap(ptr, len)?;
+            secret.region_of = Some(region_of);
+        }
+        Ok(secret)
+    }
+}
+
+/// The clone is locked separately, so every clone adds to the locked-memory footprint.
+/// `clone_from` reuses the target's locked memory, see [`SecretBox::clone_into`]. Cloning
+/// panics if the clone's memory can't be locked, see [`SecretBox::try_clone`].
+impl<S> Clone for SecretBox<S>
+where
+    S: CloneableSecret,
+{
+    fn clone(&self) -> Self {
+        self.try_clone().unwrap_or_else(|err| fail(err))
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        source.clone_into(self)
+    }
+}
+
+impl<S: Zeroize + ?Sized> ExposeSecret<S> for SecretBox<S> {
+    fn expose_secret(&self) -> SecretGuard<'_, S> {
+        self.try_expose_secret().unwrap_or_else(|err| fail(err))
+    }
+
+    fn expose_secret_mut(&mut self) -> SecretGuardMut<'_, S> {
+        self.try_expose_secret_mut().unwrap_or_else(|err| fail(err))
+    }
+
+    fn try_expose_secret(&self) -> Result<SecretGuard<'_, S>, ExposeError> {
+        self.reassert_dump_exclusion()?;
+        if let Some(protection) = &self.idle_protection {
+            protection
+                .expose(Access::ReadOnly)
+                .map_err(ExposeError::Protection)?;
+        }
+        Ok(SecretGuard {
+            data: unsafe { self.inner_secret.as_ref() },
+            idle_protection: self.idle_protection.as_ref(),
+        })
+    }
+
+    fn try_expose_secret_mut(&mut self) -> Result<SecretGuardMut<'_, S>, ExposeError> {
+        self.reassert_dump_exclusion()?;
+        if let Some(protection) = &self.idle_protection {
+            protection
+                .expose(Access::ReadWrite)
+                .map_err(ExposeError::Protection)?;
+        }
+        let relock = self.region_of.map(|region_of| Relock {
+            secret: self.inner_secret.cast(),
+            secret_len: size_of_val(unsafe { self.inner_secret.as_ref() }),
+            original: {
+                let (ptr, len) = unsafe { region_of(self.inner_secret.cast()) };
+                (ptr as usize, len)
+            },
+            region_of,
+            region: self.region,
+            heap_region: &mut self.heap_region,
+            policy: self.policy,
+        });
+        Ok(SecretGuardMut {
+            data: unsafe { self.inner_secret.as_mut() },
+            idle_protection: self.idle_protection.as_ref(),
+            relock,
+        })
+    }
+}
+
+/// Secret Guard that holds a reference to the secret.
+///
+/// For `Copy` secrets like a `u64`, dereferencing the guard, e.g. `let key = *guard;`, copies
+/// the secret to the stack or a register, where it's neither locked nor zeroized. Prefer
+/// [`Self::reveal_with`], which only lends the secret to a closure.
+pub struct SecretGuard<'a, S>
+where
+    S: Zeroize + ?Sized,
+{
+    data: &'a S,
+    idle_protection: Option<&'a IdleProtection>,
+}
+
+impl<S: Debug + Zeroize + ?Sized> Debug for SecretGuard<'_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecretGuard")
+            .field("data", &self.data)
+            .finish()
+    }
+}
+
+/// Compares the secrets in variable time, only available with the `guard-eq` feature. Use
+/// the `subtle` feature's `ConstantTimeEq` to compare secrets like passwords or MACs.
+#[cfg(feature = "guard-eq")]
+impl<S: PartialEq + Zeroize + ?Sized> PartialEq for SecretGuard<'_, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.data == other.data
+    }
+}
+
+#[cfg(feature = "guard-eq")]
+impl<S: Eq + Zeroize + ?Sized> Eq for SecretGuard<'_, S> {}
+
+impl<S: Display + Zeroize + ?Sized> Display for SecretGuard<'_, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.data)
+    }
+}
+
+impl<S> Deref for SecretGuard<'_, S>
+where
+    S: Zeroize + ?Sized,
+{
+    type Target = S;
+
+    fn deref(&self) -> &Self::Target {
+        self.data
+    }
+}
+
+impl<S: Zeroize + ?Sized> Drop for SecretGuard<'_, S> {
+    fn drop(&mut self) {
+        if let Some(protection) = self.idle_protection {
+            if let Err(err) = protection.conceal() {
+                report_drop_error(&err);
+            }
+        }
+    }
+}
+
+/// Secret Guard that holds a mutable to reference to the secret.
+pub struct SecretGuardMut<'a, S>
+where
+    S: Zeroize + ?Sized,
+{
+    data: &'a mut S,
+    idle_protection: Option<&'a IdleProtection>,
+    relock: Option<Relock<'a>>,
+}
+
+/// Locks a secret's heap data again if it was reallocated while exposed mutably.
+struct Relock<'a> {
+    secret: NonNull<()>,
+    secret_len: usize,
+    /// Start and length of the heap data when the guard was created.
+    original: (usize, usize),
+    region_of: RegionOf,
+    region: Region,
+    heap_region: &'a mut Option<Region>,
+    policy: SecretBoxBuilder,
+}
+
+// SAFETY: `secret` points to the same secret the guard holding the `Relock` borrows
+// mutably, so whether the guard is `Send` or `Sync` is decided by that borrow.
+unsafe impl Send for Relock<'_> {}
+unsafe impl Sync for Relock<'_> {}
+
+impl Relock<'_> {
+    fn relock(self) {
+        let (ptr, len) = unsafe { (self.region_of)(self.secret) };
+        let (start, end) = (ptr as usize, ptr as usize + len);
+        // Inline data like a `SmallVec`'s is locked with the secret itself
+        let secret = self.secret.as_ptr() as usize;
+        let inline = secret <= start && end <= secret + self.secret_len;
+        let covered = match *self.heap_region {
+            // The old heap region may span the secret itself, but no longer holds its data
+            _ if inline => self.heap_region.is_none(),
+            // A buffer that moved or shrank may still lie within the old region, which would
+            // keep pages locked that the allocator has taken back
+            Some(heap_region) => {
+                (start, len) == self.original
+                    && heap_region.start <= start
+                    && end <= heap_region.start + heap_region.len
+            }
+            None => len == 0,
+        };
+        if covered {
+            return;
+        }
+
+        if let Some(heap_region) = self.heap_region.take() {
+            for err in unsafe { unlock_memory(heap_region) } {
+                report_drop_error(&err);
+            }
+            // The old buffer may have shared pages with the secret itself
+            if let Err(err) = unsafe { relock_region(self.region, self.policy.lock_on_fault) } {
+                report_drop_error(&err);
+            }
+        }
+        if len != 0 && !inline {
+            match unsafe { lock_memory(ptr, len, self.policy) } {
+                Ok(heap_region) => *self.heap_region = Some(heap_region),
+                Err(err) => report_drop_error(&err),
+            }
+        }
+    }
+}
+
+impl<S: Debug + Zeroize + ?Sized> Debug for SecretGuardMut<'_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecretGuardMut")
+            .field("data", &self.data)
+            .finish()
+    }
+}
+
+/// Compares the secrets in variable time, only available with the `guard-eq` feature. Use
+/// the `subtle` feature's `ConstantTimeEq` to compare secrets like passwords or MACs.
+#[cfg(feature = "guard-eq")]
+impl<S: PartialEq + Zeroize + ?Sized> PartialEq for SecretGuardMut<'_, S> {
+    fn eq(&self, other: &Self) -> bool {
+        *self.data == *other.data
+    }
+}
+
+#[cfg(feature = "guard-eq")]
+impl<S: Eq + Zeroize + ?Sized> Eq for SecretGuardMut<'_, S> {}
+
+impl<S: Display + Zeroize + ?Sized> Display for SecretGuardMut<'_, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.data)
+    }
+}
+
+impl<S> Deref for SecretGuardMut<'_, S>
+where
+    S: Zeroize + ?Sized,
+{
+    type Target = S;
+
+    fn deref(&self) -> &Self::Target {
+        self.data
+    }
+}
+
+impl<S> DerefMut for SecretGuardMut<'_, S>
+where
+    S: Zeroize + ?Sized,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.data
+    }
+}
+
+impl<S: Zeroize + ?Sized> Drop for SecretGuardMut<'_, S> {
+    fn drop(&mut self) {
+        if let Some(relock) = self.relock.take() {
+            relock.relock();
+        }
+        if let Some(protection) = self.idle_protection {
+            if let Err(err) = protection.conceal() {
+                report_drop_error(&err);
+            }
+        }
+    }
+}
+
+impl<'a, S: Zeroize + ?Sized> SecretGuard<'a, S> {
+    /// Create a new SecretGuard instance.
+    pub fn new(data: &'a S) -> Self {
+        Self {
+            data,
+            idle_protection: None,
+        }
+    }
+
+    /// Lend the secret to `f` instead of dereferencing the guard.
+    ///
+    /// The secret can only be borrowed inside `f`, which makes copying it out of locked
+    /// memory an explicit choice of the closure rather than a stray `*guard`.
+    ///
+    /// ```
+    /// use shush_rs::{ExposeSecret, SecretBox};
+    ///
+    /// let secret = SecretBox::new(Box::new(0x2au64));
+    /// let even = secret.expose_secret().reveal_with(|key| key % 2 == 0);
+    /// assert!(even);
+    /// ```
+    pub fn reveal_with<R>(&self, f: impl FnOnce(&S) -> R) -> R {
+        f(self.data)
+    }
+
+    /// Map the guard to a part of the secret, keeping it exposed.
+    fn map<T: Zeroize + ?Sized>(self, f: impl FnOnce(&'a S) -> &'a T) -> SecretGuard<'a, T> {
+        let this = ManuallyDrop::new(self);
+        SecretGuard {
+            data: f(this.data),
+            idle_protection: this.idle_protection,
+        }
+    }
+}
+
+impl<'a, S: Zeroize + ?Sized> SecretGuardMut<'a, S> {
+    /// Create a new SecretGuard instance.
+    pub fn new(data: &'a mut S) -> Self {
+        Self {
+            data,
+            idle_protection: None,
+            relock: None,
+        }
+    }
+
+    /// Map the guard to a part of the secret, keeping it exposed.
+    fn map<T: Zeroize + ?Sized>(
+        self,
+        f: impl FnOnce(&'a mut S) -> &'a mut T,
+    ) -> SecretGuardMut<'a, T> {
+        let mut this = ManuallyDrop::new(self);
+        let data = unsafe { ptr::read(&this.data) };
+        SecretGuardMut {
+            data: f(data),
+            idle_protection: this.idle_protection.take(),
+            relock: this.relock.take(),
+        }
+    }
+}
+
+/// Marker trait for secrets which are allowed to be cloned
+///
+/// Cloning is opt-in per type, since every clone is another copy of the secret in locked
+/// memory. The common std types implement it, implement it for your own types with
+/// `impl CloneableSecret for MyKey {}`.
+pub trait CloneableSecret: Clone + Zeroize {}
+
+impl CloneableSecret for String {}
+
+impl<T: CloneableSecret> CloneableSecret for Vec<T> {}
+
+impl<T: CloneableSecret, const N: usize> CloneableSecret for [T; N] {}
+
+macro_rules! impl_cloneable_secret {
+    ($($ty:ty),*) => {
+        $(impl CloneableSecret for $ty {})*
+    };
+}
+
+impl_cloneable_secret!(
+    u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, bool, char
+);
+
+/// Marker trait for secrets whose `Zeroize` impl scrubs all the memory they own
+///
+/// A custom `Zeroize` impl may well skip heap data, e.g. by replacing a `Vec` instead of
+/// zeroizing it, leaving the secret in freed memory. This trait asserts it doesn't, which
+/// [`SecretBox::new_deep`] checks at compile time. It's implemented for `String`, `Vec<T>`,
+/// arrays and primitive types, implement it for your own types with
+/// `impl DeepZeroize for MyKey {}`.
+///
+/// ```compile_fail
+/// use shush_rs::{SecretBox, Zeroize};
+///
+/// struct Shallow(Vec<u8>);
+///
+/// impl Zeroize for Shallow {
+///     fn zeroize(&mut self) {
+///         self.0 = Vec::new();
+///     }
+/// }
+///
+/// // error[E0277]: the trait bound `Shallow: DeepZeroize` is not satisfied
+/// let secret = SecretBox::new_deep(Box::new(Shallow(vec![1, 2, 3])));
+/// ```
+pub trait DeepZeroize: Zeroize {}
+
+impl DeepZeroize for String {}
+
+impl<T: DeepZeroize> DeepZeroize for Vec<T> {}
+
+impl<T: DeepZeroize, const N: usize> DeepZeroize for [T; N] {}
+
+macro_rules! impl_deep_zeroize {
+    ($($ty:ty),*) => {
+        $(impl DeepZeroize for $ty {})*
+    };
+}
+
+impl_deep_zeroize!(
+    u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, bool, char
+);
+
+/// Types whose data lives in memory outside of the value itself, e.g. in a heap buffer.
+///
+/// `size_of_val` only covers the top-level value, for a `String` that's its pointer, length
+/// and capacity but not its characters. Implementing this trait lets
+/// [`SecretBox::new_with_region`] lock the memory actually holding the secret.
+pub trait LockableRegion {
+    /// Start and length in bytes of the memory holding the value's data.
+    fn region(&self) -> (*const u8, usize);
+}
+
+impl LockableRegion for String {
+    fn region(&self) -> (*const u8, usize) {
+        (self.as_ptr(), self.capacity())
+    }
+}
+
+impl<T> LockableRegion for Vec<T> {
+    fn region(&self) -> (*const u8, usize) {
+        (self.as_ptr().cast(), self.capacity() * size_of::<T>())
+    }
+}
+
+impl<T, const N: usize> LockableRegion for [T; N] {
+    fn region(&self) -> (*const u8, usize) {
+        (self.as_ptr().cast(), size_of_val(self))
+    }
+}
+
+/// Define a newtype secret wrapping a `String`, `Vec<T>` or array, e.g. to tell apart API
+/// keys and passwords in signatures.
+///
+/// `define_secret!(pub ApiKey(String));` defines `pub struct ApiKey(String)` implementing
+/// [`Zeroize`], `Clone`, [`CloneableSecret`], [`DeepZeroize`] and [`LockableRegion`] by
+/// delegating to the inner value, plus `From` for the inner value and a `Debug` that prints
+/// `ApiKey([REDACTED])`. Attributes and the field's visibility are passed through. The inner
+/// type has to implement the delegated traits.
+///
+/// ```
+/// use shush_rs::{define_secret, ExposeSecret, SecretBox};
+///
+/// define_secret!(
+///     /// Key for the payment provider's API.
+///     pub ApiKey(pub String)
+/// );
+///
+/// let key = SecretBox::new_with_region(Box::new(ApiKey::from(String::from("sk_live_1234"))));
+/// let copy = key.clone();
+/// assert_eq!(copy.expose_secret().0, "sk_live_1234");
+/// assert_eq!(format!("{:?}", *key.expose_secret()), "ApiKey([REDACTED])");
+/// ```
+#[macro_export]
+macro_rules! define_secret {
+    ($(#[$meta:meta])* $vis:vis $name:ident($field_vis:vis $inner:ty) $(;)?) => {
+        $(#[$meta])*
+        $vis struct $name($field_vis $inner);
+
+        impl $crate::Zeroize for $name {
+            fn zeroize(&mut self) {
+                $crate::Zeroize::zeroize(&mut self.0)
+            }
+        }
+
+        impl ::core::clone::Clone for $name {
+            fn clone(&self) -> Self {
+                Self(self.0.clone())
+            }
+        }
+
+        impl $crate::CloneableSecret for $name {}
+
+        impl $crate::DeepZeroize for $name {}
+
+        impl $crate::LockableRegion for $name {
+            fn region(&self) -> (*const u8, usize) {
+                $crate::LockableRegion::region(&self.0)
+            }
+        }
+
+        impl ::core::convert::From<$inner> for $name {
+            fn from(inner: $inner) -> Self {
+                Self(inner)
+            }
+        }
+
+        impl ::core::fmt::Debug for $name {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.write_str(concat!(stringify!($name), "([REDACTED])"))
+            }
+        }
+    };
+}
+
+/// Create a SecretGuard that holds a reference to the secret
+///
+/// Guards borrow the secret, so the usual borrowing rules decide how it can be exposed at
+/// compile time, no runtime tracking is needed: any number of [`SecretGuard`]s can be alive
+/// at once through `&self`, a [`SecretGuardMut`] needs `&mut self` and is exclusive.
+///
+/// ```compile_fail
+/// use shush_rs::{ExposeSecret, SecretBox};
+///
+/// let mut secret = SecretBox::new(Box::new([0u8; 32]));
+/// let guard = secret.expose_secret();
+/// // error[E0502]: cannot borrow `secret` as mutable because it is also borrowed as immutable
+/// secret.expose_secret_mut()[0] = 1;
+/// drop(guard);
+/// ```
+///
+/// Sharing a secret between threads, e.g. in an `Arc`, only ever gives out `&self`, so
+/// shared exposure is read-only. See [`SecretBox`] for its thread safety.
+pub trait ExposeSecret<S: Zeroize + ?Sized> {
+    /// Expose secret as non-mutable.
+    fn expose_secret(&self) -> SecretGuard<'_, S>;
+
+    /// Expose secret as mutable.
+    fn expose_secret_mut(&mut self) -> SecretGuardMut<'_, S>;
+
+    /// Expose secret as non-mutable, returning an error instead of panicking if the secret
+    /// can't be made accessible.
+    fn try_expose_secret(&self) -> Result<SecretGuard<'_, S>, ExposeError> {
+        Ok(self.expose_secret())
+    }
+
+    /// Expose secret as mutable, returning an error instead of panicking if the secret
+    /// can't be made accessible.
+    fn try_expose_secret_mut(&mut self) -> Result<SecretGuardMut<'_, S>, ExposeError> {
+        Ok(self.expose_secret_mut())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        cell::{Cell, RefCell},
+        marker::PhantomData,
+    };
+
+    use super::*;
+
+    // Fails to compile if secrets can't be shared between threads
+    const _: fn() = || {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SecretBox<Vec<u8>>>();
+        assert_send_sync::<SecretSlice<u8>>();
+        assert_send_sync::<SecretString>();
+    };
+
+    /// Allocator counting the allocations made by the current thread, so tests running in
+    /// parallel don't interfere.
+    struct CountingAlloc;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+        static LIVE_ALLOCATIONS: Cell<isize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAlloc {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            let _ = LIVE_ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            let _ = LIVE_ALLOCATIONS.try_with(|count| count.set(count.get() - 1));
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: CountingAlloc = CountingAlloc;
+
+    #[derive(Debug, Clone, Default)]
+    struct TestSecret {
+        data: Vec<u8>,
+    }
+
+    impl TestSecret {
+        fn new(size: usize) -> Self {
+            let mut data = vec![0; size];
+            data[0] = 1;
+            Self { data }
+        }
+
+        fn check_non_zero(&self) -> bool {
+            self.data.iter().any(|&x| x != 0)
         }
     }
 
@@ -349,6 +2519,83 @@ mod tests {
         }
     }
 
+    /// Needs a larger alignment than any page size in use offers.
+    #[cfg(any(unix, windows))]
+    #[repr(align(1048576))]
+    struct OverAligned([u8; 32]);
+
+    #[cfg(any(unix, windows))]
+    impl Zeroize for OverAligned {
+        fn zeroize(&mut self) {
+            self.0.zeroize();
+        }
+    }
+
+    #[cfg(any(unix, windows))]
+    impl OverAligned {
+        fn boxed() -> Box<Self> {
+            // Built in place, the value is too large to be moved through the stack.
+            // SAFETY: all zero bytes are a valid byte array
+            let mut boxed = unsafe { Box::<Self>::new_zeroed().assume_init() };
+            boxed.0 = [1; 32];
+            boxed
+        }
+    }
+
+    thread_local! {
+        static DROP_EVENTS: RefCell<Vec<&'static str>> = const {
+            RefCell::new(Vec::new())
+        };
+    }
+
+    /// Secret with a `Drop` of its own, recording the order of zeroizing and dropping.
+    struct Handle([u8; 16]);
+
+    impl Zeroize for Handle {
+        fn zeroize(&mut self) {
+            #[cfg(target_os = "linux")]
+            if memory::is_range_locked(self.0.as_ptr(), 16).unwrap() {
+                DROP_EVENTS.with_borrow_mut(|events| events.push("zeroize while locked"));
+            }
+            self.0.zeroize();
+            DROP_EVENTS.with_borrow_mut(|events| events.push("zeroize"));
+        }
+    }
+
+    impl Drop for Handle {
+        fn drop(&mut self) {
+            assert_eq!(self.0, [0; 16], "dropped before zeroizing");
+            DROP_EVENTS.with_borrow_mut(|events| events.push("drop"));
+        }
+    }
+
+    #[test]
+    fn test_secret_box_drop_order() {
+        // Dedicated pages aren't shared with any other secret that could keep them locked
+        for secret_box in [
+            SecretBox::new_locked_pages(Box::new(Handle([1; 16]))),
+            SecretBox::new_guarded(Box::new(Handle([1; 16]))),
+            SecretBox::new_protected(Box::new(Handle([1; 16]))),
+        ] {
+            drop(secret_box);
+            let events = DROP_EVENTS.with_borrow_mut(mem::take);
+            assert_eq!(events, ["zeroize", "drop"]);
+        }
+
+        let secret_box = SecretBox::new(Box::new(Handle([1; 16])));
+        drop(secret_box);
+        let events = DROP_EVENTS.with_borrow_mut(mem::take);
+        assert_eq!(events.iter().filter(|&&event| event == "drop").count(), 1);
+        assert_eq!(events.last(), Some(&"drop"));
+
+        // The caller's memory isn't freed, but the value is still dropped once
+        let mut slot = ManuallyDrop::new(Handle([1; 16]));
+        let secret_box = unsafe { SecretBox::from_locked_ptr(NonNull::from(&mut *slot), false) };
+        drop(secret_box);
+        let events = DROP_EVENTS.with_borrow_mut(mem::take);
+        assert_eq!(events.iter().filter(|&&event| event == "drop").count(), 1);
+    }
+
     #[test]
     fn test_secret_box_drop_zeroizes() {
         let secret = Box::new(TestSecret::new(10));
@@ -356,11 +2603,35 @@ mod tests {
         assert!((*secret_box.expose_secret()).check_non_zero());
 
         drop(secret_box);
+        // `TestSecret::zeroize` frees its buffer, leaving an empty `Vec` behind
+        let recorded = ZEROIZED_ON_DROP.with(|recorded| recorded.take());
+        assert_eq!(recorded.len(), size_of::<TestSecret>());
+
+        let secret_box = SecretBox::new(Box::new([0xAAu8; 64]));
+        drop(secret_box);
+        let recorded = ZEROIZED_ON_DROP.with(|recorded| recorded.take());
+        assert_eq!(recorded, [0; 64]);
 
-        // Verify that secret is zeroized after drop
-        // This requires checking the memory, which is not straightforward in Rust.
-        // Here we rely on the zeroize trait to ensure it zeroizes.
-        assert!(TestSecret::default().check_zero());
+        // The heap data is checked as well
+        let mut data = Vec::with_capacity(64);
+        data.extend_from_slice(&[0xAAu8; 32]);
+        let secret_box = SecretVec::from(data);
+        drop(secret_box);
+        let recorded = ZEROIZED_ON_DROP.with(|recorded| recorded.take());
+        assert_eq!(recorded.len(), size_of::<Vec<u8>>() + 64);
+        assert!(recorded[size_of::<Vec<u8>>()..]
+            .iter()
+            .all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn test_secret_box_drop_zeroizes_external() {
+        // Memory the caller owns outlives the box, so it can be checked directly
+        let mut slot = [0xAAu8; 64];
+        let secret_box = unsafe { SecretBox::from_locked_ptr(NonNull::from(&mut slot), false) };
+        assert_eq!(*secret_box.expose_secret(), [0xAA; 64]);
+        drop(secret_box);
+        assert_eq!(slot, [0; 64]);
     }
 
     #[test]
@@ -376,12 +2647,742 @@ mod tests {
         assert_eq!((*secret_box.expose_secret()), String::from("Encrypted 2"));
     }
 
+    #[test]
+    fn test_secret_box_try_default() {
+        let secret_box = SecretBox::<[u8; 32]>::try_default().unwrap();
+        assert_eq!(*secret_box.expose_secret(), [0; 32]);
+        assert_eq!(secret_box.region.locked, !cfg!(feature = "no-mlock"));
+    }
+
+    #[test]
+    fn test_secret_box_try_new() {
+        let secret_box = SecretBox::try_new(Box::new(TestSecret::new(10)))
+            .expect("Failed to lock secret memory");
+        assert!((*secret_box.expose_secret()).check_non_zero());
+    }
+
+    #[test]
+    fn test_secret_box_new_best_effort() {
+        let (secret_box, locked) = SecretBox::new_best_effort(Box::new(TestSecret::new(10)));
+        assert_eq!(locked, secret_box.region.locked);
+        assert!((*secret_box.expose_secret()).check_non_zero());
+    }
+
+    #[test]
+    fn test_secret_box_new_unlocked() {
+        let secret_box = SecretBox::new_unlocked(Box::new(TestSecret::new(4)));
+        assert!(!secret_box.region.locked);
+        assert!(secret_box.expose_secret().check_non_zero());
+    }
+
+    #[test]
+    fn test_secret_box_map() {
+        let secret_box = SecretBox::new(Box::new(TestSecret::new(10)));
+        let derived = secret_box.map(|secret| secret.data.len() as u64 * 2);
+        assert_eq!(*derived.expose_secret(), 20);
+    }
+
+    #[test]
+    fn test_secret_box_into_inner() {
+        let secret_box = SecretBox::new(Box::new(TestSecret::new(10)));
+        assert!(secret_box.into_inner().check_non_zero());
+
+        let secret_box = SecretBox::new_protected(Box::new(TestSecret::new(10)));
+        assert!(secret_box.into_inner().check_non_zero());
+    }
+
+    #[test]
+    fn test_secret_box_destroy() {
+        assert_eq!(SecretBox::new(Box::new([1u8; 32])).destroy(), Ok(()));
+        assert_eq!(SecretVec::from(vec![1u8; 8192]).destroy(), Ok(()));
+        assert_eq!(
+            SecretBox::new_protected(Box::new([1u8; 32])).destroy(),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_secret_box_zeroize_pattern() {
+        let mut secret_vec = SecretVec::from(vec![1u8; 16]);
+        secret_vec.zeroize_with(ZeroizePattern::Random);
+        assert!(secret_vec.expose_secret().is_empty());
+
+        let secret_box =
+            SecretBox::new(Box::new([1u8; 32])).with_zeroize_pattern(ZeroizePattern::MultiPass);
+        let cloned = secret_box.clone();
+        assert!(matches!(
+            cloned.drop_pattern,
+            Some((ZeroizePattern::MultiPass, _))
+        ));
+        assert_eq!(*cloned.expose_secret(), [1; 32]);
+    }
+
+    #[test]
+    fn test_secret_box_leak() {
+        let secret_box = SecretBox::new_protected(Box::new([1u8; 32]));
+        let leaked = secret_box.leak_mut();
+        leaked[0] = 2;
+        assert_eq!(leaked[..2], [2, 1]);
+
+        let mut slot = [3u8; 32];
+        let adopted = unsafe { SecretBox::from_locked_ptr(NonNull::from(&mut slot), true) };
+        let leaked = adopted.leak();
+        assert_ne!(leaked.as_ptr(), slot.as_ptr());
+        assert_eq!(*leaked, [3; 32]);
+    }
+
+    #[test]
+    fn test_secret_box_replace() {
+        let mut secret_box = SecretBox::new(Box::new([1u8; 32])).with_debug_label("key");
+        let new = SecretBox::new(Box::new([2u8; 32]));
+        let new_ptr = new.inner_secret;
+
+        secret_box.replace(new);
+        assert_eq!(secret_box.inner_secret, new_ptr);
+        assert_eq!(*secret_box.expose_secret(), [2; 32]);
+        assert_eq!(secret_box.debug_label, Some("key"));
+    }
+
+    #[test]
+    fn test_secret_box_try_clone() {
+        let secret_vec = SecretVec::from(vec![1u8; 32]).with_debug_label("key");
+        let cloned = secret_vec.try_clone().unwrap();
+        assert_eq!(*cloned.expose_secret(), [1; 32]);
+        assert_eq!(cloned.debug_label, Some("key"));
+        assert!(cloned.heap_region.is_some());
+
+        let secret_string = SecretString::from(String::from("token"));
+        assert_eq!(
+            &*secret_string.try_clone().unwrap().expose_secret(),
+            "token"
+        );
+    }
+
+    #[test]
+    fn test_secret_box_clone() {
+        let secret_box = SecretBox::new(Box::new([7u8; 32]));
+        assert_eq!(*secret_box.clone().expose_secret(), [7; 32]);
+
+        let secret_vec = SecretVec::from(vec![1u64, 2, 3]);
+        let cloned = secret_vec.clone();
+        assert_eq!(*cloned.expose_secret(), [1, 2, 3]);
+        assert!(cloned.heap_region.is_some());
+    }
+
+    #[test]
+    fn test_secret_box_clone_into() {
+        let source = SecretVec::from(vec![1u8; 32]);
+        let mut target = SecretVec::from(vec![2u8; 32]);
+        let buffer = target.expose_secret().as_ptr();
+        let allocations = ALLOCATIONS.get();
+
+        source.clone_into(&mut target);
+        assert_eq!(ALLOCATIONS.get(), allocations);
+        assert_eq!(target.expose_secret().as_ptr(), buffer);
+        assert_eq!(*target.expose_secret(), [1; 32]);
+
+        let longer = SecretVec::from(vec![3u8; 64]);
+        target.clone_from(&longer);
+        assert_eq!(*target.expose_secret(), [3; 64]);
+        let heap_region = target.heap_region.expect("Vec buffer is not locked");
+        let (ptr, len) = target.expose_secret().region();
+        assert!(heap_region.start <= ptr as usize);
+        assert!(ptr as usize + len <= heap_region.start + heap_region.len);
+    }
+
+    #[test]
+    fn test_secret_box_as_pinned_ref() {
+        let secret_box = SecretBox::new(Box::new([1u8; 32]));
+        let address = secret_box.as_pinned_ref().as_ptr();
+
+        // Moving the box doesn't move the secret
+        let moved = Box::new(secret_box);
+        let pinned = moved.as_pinned_ref();
+        assert_eq!(pinned.as_ptr(), address);
+        assert_eq!(*pinned, [1; 32]);
+    }
+
+    #[test]
+    fn test_secret_box_with_exposed() {
+        let mut secret_box = SecretBox::new_protected(Box::new([1u8; 32]));
+        secret_box.with_exposed_mut(|secret| secret[0] = 2);
+        assert_eq!(secret_box.with_exposed(|secret| secret[0]), 2);
+
+        let protection = secret_box.idle_protection.as_ref().unwrap();
+        assert_eq!(*protection.exposed.lock().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_secret_box_with_range() {
+        let mut material = [0u8; 48];
+        material[32..].fill(1);
+        let secret_array = SecretArray::<u8, 48>::from_slice(&material).unwrap();
+        assert_eq!(secret_array.with_range(32..48, |iv| iv.to_vec()), [1; 16]);
+        assert_eq!(secret_array.with_range(0..32, |key| key.len()), 32);
+
+        let secret_vec = SecretVec::from(vec![1u8, 2, 3]);
+        assert_eq!(secret_vec.with_range(1..3, |bytes| bytes.to_vec()), [2, 3]);
+        assert!(secret_vec.with_range(3..3, <[u8]>::is_empty));
+    }
+
+    #[test]
+    fn test_secret_box_expose_owned() {
+        let secret_vec = SecretVec::from(vec![1u8, 2, 3]);
+        let owned = secret_vec.expose_owned();
+        assert_eq!(*owned, [1, 2, 3]);
+        assert_ne!(owned.as_ptr(), secret_vec.expose_secret().as_ptr());
+
+        let secret_array = SecretArray::<u8, 4>::from_slice(&[4; 4]).unwrap();
+        assert_eq!(*secret_array.expose_owned(), [4; 4]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_secret_box_with_range_out_of_bounds() {
+        let secret_vec = SecretVec::from(vec![1u8, 2, 3]);
+        secret_vec.with_range(2..4, |_| ());
+    }
+
+    #[test]
+    fn test_secret_guard_reveal_with() {
+        let secret_box = SecretBox::new_protected(Box::new(42u64));
+        assert_eq!(
+            secret_box.expose_secret().reveal_with(|secret| secret + 1),
+            43
+        );
+
+        let secret_string = SecretString::new(String::from("Encrypted"));
+        assert_eq!(secret_string.expose_secret().reveal_with(str::len), 9);
+    }
+
+    #[test]
+    fn test_secret_box_zero_sized() {
+        let secret_box = SecretBox::<PhantomData<u8>>::default();
+        assert_eq!(secret_box.region.len, 0);
+        assert!(!secret_box.region.locked);
+        drop(secret_box);
+
+        let secret_box = SecretBox::new_protected(Box::new(PhantomData::<u8>));
+        drop(secret_box.expose_secret());
+    }
+
+    #[test]
+    fn test_secret_box_new_locked_pages() {
+        let secret_box = SecretBox::new_locked_pages(Box::new([1u8; 32]));
+        let page_size = memory::page_size().unwrap();
+        let ptr = secret_box.inner_secret.as_ptr() as usize;
+        assert_eq!(ptr % page_size, 0);
+        assert_eq!(
+            (secret_box.region.start, secret_box.region.len),
+            (ptr, page_size)
+        );
+        assert!(secret_box.idle_protection.is_none());
+        assert_eq!(*secret_box.expose_secret(), [1; 32]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_secret_box_new_guarded() {
+        let secret_box = SecretBox::new_guarded(Box::new([1u8; 32]));
+        assert_eq!(*secret_box.expose_secret(), [1; 32]);
+
+        // Reading one byte past the secret must fault, try it in a child process
+        let past_end = unsafe { secret_box.inner_secret.as_ptr().cast::<u8>().add(32) };
+        let pid = unsafe { libc::fork() };
+        assert_ne!(pid, -1);
+        if pid == 0 {
+            unsafe {
+                ptr::read_volatile(past_end);
+                libc::_exit(0);
+            }
+        }
+        let mut status = 0;
+        assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+        assert!(libc::WIFSIGNALED(status));
+        assert_eq!(libc::WTERMSIG(status), libc::SIGSEGV);
+    }
+
+    #[cfg(all(unix, feature = "fail-hard"))]
+    #[test]
+    fn test_fail_hard_aborts() {
+        let pid = unsafe { libc::fork() };
+        assert_ne!(pid, -1);
+        if pid == 0 {
+            // Catching the failure must not let the child carry on
+            let _ = std::panic::catch_unwind(|| fail(SecretBoxError::Unsupported));
+            unsafe { libc::_exit(0) };
+        }
+        let mut status = 0;
+        assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+        assert!(libc::WIFSIGNALED(status));
+        assert_eq!(libc::WTERMSIG(status), libc::SIGABRT);
+    }
+
+    #[cfg(all(unix, not(feature = "no-mlock")))]
+    #[test]
+    fn test_memlock_budget() {
+        // The budget is process-wide, so it's only changed in a child process
+        let pid = unsafe { libc::fork() };
+        assert_ne!(pid, -1);
+        if pid == 0 {
+            set_memlock_budget(0);
+            let refused = matches!(
+                SecretBox::try_new(Box::new([1u8; 32])),
+                Err(SecretBoxError::MemlockBudget { budget: 0, .. })
+            );
+            let best_effort = !SecretBox::new_best_effort(Box::new([1u8; 32])).1;
+            set_memlock_budget(usize::MAX);
+            let unlimited = SecretBox::try_new(Box::new([1u8; 32])).is_ok();
+            unsafe {
+                libc::_exit(if refused && best_effort && unlimited {
+                    0
+                } else {
+                    1
+                })
+            };
+        }
+        let mut status = 0;
+        assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+        assert!(libc::WIFEXITED(status));
+        assert_eq!(libc::WEXITSTATUS(status), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_disable_memory_locking() {
+        // Locking is disabled process-wide, so only in a child process
+        let pid = unsafe { libc::fork() };
+        assert_ne!(pid, -1);
+        if pid == 0 {
+            disable_memory_locking();
+            let secret_box = SecretBox::new(Box::new([1u8; 32]));
+            let guarded = SecretBox::new_guarded(Box::new([1u8; 32]));
+            let unlocked = !secret_box.region.locked && !guarded.region.locked;
+            let usable = *secret_box.expose_secret() == [1; 32];
+            unsafe { libc::_exit(if unlocked && usable { 0 } else { 1 }) };
+        }
+        let mut status = 0;
+        assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+        assert!(libc::WIFEXITED(status));
+        assert_eq!(libc::WEXITSTATUS(status), 0);
+    }
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn test_secret_box_new_page_aligned() {
+        let page_size = memory::page_size().unwrap();
+        let secret_box = SecretBox::new_page_aligned(Box::new([1u8; 32]));
+        assert_eq!(secret_box.backing_kind(), BackingKind::PageAligned);
+        // The locked region is exactly the secret's own page
+        let ptr = secret_box.expose_secret().as_ptr() as usize;
+        assert_eq!(ptr % page_size, 0);
+        assert_eq!(secret_box.region.start, ptr);
+        assert_eq!(secret_box.region.len, page_size);
+        assert!(secret_box.region.locked || cfg!(feature = "no-mlock"));
+        assert_eq!(*secret_box.expose_secret(), [1; 32]);
+
+        let cloned = secret_box.clone();
+        assert_eq!(cloned.backing_kind(), BackingKind::PageAligned);
+        assert_eq!(*cloned.expose_secret(), [1; 32]);
+
+        let large = SecretBox::new_page_aligned(Box::new([2u8; 5000]));
+        assert_eq!(large.region.len, 5000usize.div_ceil(page_size) * page_size);
+        assert_eq!(*large.into_inner(), [2; 5000]);
+    }
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn test_secret_box_pages_reject_over_aligned() {
+        let over_aligned = |result: Result<SecretBox<OverAligned>, SecretBoxError>| {
+            matches!(result, Err(SecretBoxError::OverAligned { .. }))
+        };
+        assert!(over_aligned(SecretBox::try_new_locked_pages(
+            OverAligned::boxed()
+        )));
+        assert!(over_aligned(SecretBox::try_new_protected(
+            OverAligned::boxed()
+        )));
+        assert!(over_aligned(
+            SecretBoxBuilder::new()
+                .guard_pages(true)
+                .try_build(OverAligned::boxed())
+        ));
+    }
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn test_secret_box_page_aligned_rejects_over_aligned() {
+        let page_size = memory::page_size().unwrap();
+        assert!(matches!(
+            SecretBox::try_new_page_aligned(OverAligned::boxed()),
+            Err(SecretBoxError::OverAligned { align: 1048576, page_size: size })
+                if size == page_size
+        ));
+    }
+
+    #[test]
+    fn test_secret_box_with_mut_ptr() {
+        let mut secret_vec = SecretVec::from(vec![0u8; 16]);
+        let buffer = secret_vec.expose_secret().as_ptr();
+        let len = unsafe {
+            secret_vec.with_mut_ptr(|ptr, len| {
+                assert_eq!(ptr.cast_const(), buffer);
+                ptr.write_bytes(1, len);
+                len
+            })
+        };
+        assert_eq!(len, 16);
+        assert_eq!(*secret_vec.expose_secret(), [1; 16]);
+
+        // Idle-protected pages are writable while the pointer is handed out
+        let mut protected = SecretBox::new_protected(Box::new([0u8; 32]));
+        unsafe { protected.with_mut_ptr(|ptr, len| ptr.write_bytes(2, len)) };
+        assert_eq!(*protected.expose_secret(), [2; 32]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_secret_box_locked_bytes() {
+        let page_size = memory::page_size().unwrap();
+        // Scale expectations down to nothing when locking is compiled out
+        let locked = usize::from(!cfg!(feature = "no-mlock"));
+
+        // A small secret may straddle a page boundary
+        let secret_box = SecretBox::new(Box::new([1u8; 32]));
+        assert!([locked * page_size, locked * 2 * page_size].contains(&secret_box.locked_bytes()));
+        assert_eq!(
+            SecretBox::new_unlocked(Box::new([1u8; 32])).locked_bytes(),
+            0
+        );
+
+        let secret_box = SecretBox::new_locked_pages(Box::new([1u8; 32]));
+        assert_eq!(secret_box.locked_bytes(), locked * page_size);
+
+        let secret_vec = SecretVec::from(vec![1u8; 3 * page_size]);
+        assert!(secret_vec.locked_bytes() >= locked * 4 * page_size);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_secret_box_assert_resident() {
+        let secret_box = SecretBox::new_locked_pages(Box::new([1u8; 32]));
+        if !cfg!(feature = "no-mlock") {
+            secret_box.assert_resident().unwrap();
+        }
+
+        // Dedicated pages aren't shared with any other secret that could have locked them
+        let secret_box = SecretBoxBuilder::new()
+            .mlock(false)
+            .guard_pages(true)
+            .build(Box::new([1u8; 32]));
+        assert!(matches!(
+            secret_box.assert_resident(),
+            Err(ResidencyError::NotLocked(address)) if address == secret_box.region.start
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_secret_box_is_locked() {
+        let locked = !cfg!(feature = "no-mlock");
+
+        let secret_box = SecretBox::new(Box::new([1u8; 32]));
+        assert_eq!(secret_box.is_locked().unwrap(), locked);
+
+        let secret_vec = SecretVec::from(vec![1u8; 8192]);
+        assert_eq!(secret_vec.is_locked().unwrap(), locked);
+
+        // Dedicated pages aren't shared with any other secret that could have locked them
+        let unlocked = SecretBox::new_locked_pages(Box::new([1u8; 32]));
+        unsafe { libc::munlock(unlocked.region.start as *const _, unlocked.region.len) };
+        assert!(!unlocked.is_locked().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_secret_box_from_locked_ptr() {
+        let page_size = memory::page_size().unwrap();
+        let start = memory::map_pages(page_size).unwrap();
+        assert_eq!(unsafe { libc::mlock(start as *const _, page_size) }, 0);
+        let ptr = NonNull::new(start as *mut [u8; 32]).unwrap();
+        unsafe { ptr.as_ptr().write([1; 32]) };
+
+        let secret_box = unsafe { SecretBox::from_locked_ptr(ptr, true) };
+        assert_eq!(*secret_box.expose_secret(), [1; 32]);
+        drop(secret_box);
+
+        // The secret is zeroized, the memory stays the caller's and stays locked
+        assert_eq!(unsafe { ptr.as_ptr().read() }, [0; 32]);
+        #[cfg(target_os = "linux")]
+        assert!(memory::is_range_locked(start as *const u8, page_size).unwrap());
+
+        unsafe { ptr.as_ptr().write([2; 32]) };
+        let secret_box = unsafe { SecretBox::from_locked_ptr(ptr, false) };
+        assert_eq!(*secret_box.into_inner(), [2; 32]);
+        assert_eq!(unsafe { ptr.as_ptr().read() }, [0; 32]);
+
+        unsafe { memory::unmap_pages(start, page_size).unwrap() };
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_expose_secret_reasserts_dump_exclusion() {
+        fn dump_excluded(start: usize) -> bool {
+            let smaps = std::fs::read_to_string("/proc/self/smaps").unwrap();
+            // The mapping may have been merged with a neighbouring one, find the one containing it
+            let contains_start = |line: &str| {
+                let range = line.split_whitespace().next().unwrap_or_default();
+                range.split_once('-').is_some_and(|(from, to)| {
+                    let parse = |address| usize::from_str_radix(address, 16);
+                    matches!((parse(from), parse(to)), (Ok(from), Ok(to)) if (from..to).contains(&start))
+                })
+            };
+            smaps
+                .lines()
+                .skip_while(|line| !contains_start(line))
+                .find_map(|line| line.strip_prefix("VmFlags:"))
+                .unwrap()
+                .split_whitespace()
+                .any(|flag| flag == "dd")
+        }
+
+        let secret_box = SecretBox::new_locked_pages(Box::new([1u8; 32]));
+        let Region { start, len, .. } = secret_box.region;
+        assert!(dump_excluded(start));
+
+        unsafe { libc::madvise(start as *mut _, len, libc::MADV_DODUMP) };
+        assert!(!dump_excluded(start));
+        assert_eq!(*secret_box.expose_secret(), [1; 32]);
+        assert!(dump_excluded(start));
+    }
+
+    #[cfg(all(unix, feature = "atfork"))]
+    #[test]
+    fn test_secret_box_register_atfork_zeroize() {
+        let secret_box = SecretBox::new_protected(Box::new([1u8; 32]));
+        secret_box.register_atfork_zeroize();
+        let secret_vec = SecretVec::from(vec![1u8; 32]);
+        secret_vec.register_atfork_zeroize();
+
+        let pid = unsafe { libc::fork() };
+        assert_ne!(pid, -1);
+        if pid == 0 {
+            let zeroized =
+                *secret_box.expose_secret() == [0; 32] && secret_vec.expose_secret().is_empty();
+            unsafe { libc::_exit(if zeroized { 0 } else { 1 }) };
+        }
+        let mut status = 0;
+        assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+        assert!(libc::WIFEXITED(status));
+        assert_eq!(libc::WEXITSTATUS(status), 0);
+
+        assert_eq!(*secret_box.expose_secret(), [1; 32]);
+        assert_eq!(*secret_vec.expose_secret(), [1; 32]);
+    }
+
+    #[test]
+    fn test_secret_box_expose_secret_shared() {
+        let secret_box = std::sync::Arc::new(SecretBox::new(Box::new(String::from("Encrypted"))));
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let secret_box = secret_box.clone();
+                std::thread::spawn(move || secret_box.expose_secret().len())
+            })
+            .collect();
+
+        for reader in readers {
+            assert_eq!(reader.join().unwrap(), "Encrypted".len());
+        }
+    }
+
+    #[test]
+    fn test_secret_box_expose_secret_shared_protected() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+        let secret_box = std::sync::Arc::new(SecretBox::new_protected(Box::new([1u8; 32])));
+        assert_send_sync(&secret_box);
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let secret_box = secret_box.clone();
+                std::thread::spawn(move || (0..100).all(|_| *secret_box.expose_secret() == [1; 32]))
+            })
+            .collect();
+
+        for reader in readers {
+            assert!(reader.join().unwrap());
+        }
+        let protection = secret_box.idle_protection.as_ref().unwrap();
+        assert_eq!(*protection.exposed.lock().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_secret_box_new_protected() {
+        let mut secret_box = SecretBox::new_protected(Box::new([1u8; 32]));
+        assert_eq!(*secret_box.expose_secret(), [1u8; 32]);
+
+        secret_box.expose_secret_mut()[0] = 2;
+
+        {
+            let first = secret_box.expose_secret();
+            let second = secret_box.expose_secret();
+            drop(first);
+            // The pages must stay readable until the last guard is dropped
+            assert_eq!(second[0], 2);
+        }
+
+        assert_eq!(secret_box.expose_secret()[..2], [2, 1]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_secret_box_new_protected_inaccessible_while_idle() {
+        fn permissions(start: usize) -> String {
+            let maps = std::fs::read_to_string("/proc/self/maps").unwrap();
+            maps.lines()
+                .find(|line| line.starts_with(&format!("{:x}-", start)))
+                .and_then(|line| line.split_whitespace().nth(1))
+                .unwrap()
+                .to_string()
+        }
+
+        let secret_box = SecretBox::new_protected(Box::new(42u64));
+        let start = secret_box.region.start;
+        assert_eq!(permissions(start), "---p");
+
+        let exposed = secret_box.expose_secret();
+        assert_eq!(permissions(start), "r--p");
+        assert_eq!(*exposed, 42);
+        drop(exposed);
+
+        assert_eq!(permissions(start), "---p");
+
+        // Overlapping guards keep the pages readable until the last one is dropped
+        let first = secret_box.expose_secret();
+        let second = secret_box.expose_secret();
+        assert_eq!(permissions(start), "r--p");
+        drop(first);
+        assert_eq!(permissions(start), "r--p");
+        assert_eq!(*second, 42);
+        drop(second);
+        assert_eq!(permissions(start), "---p");
+    }
+
     #[test]
     fn test_secret_box_new_with_ctr() {
         let secret_box = SecretBox::new_with_ctr(|| TestSecret::new(10));
         assert!((*secret_box.expose_secret()).check_non_zero());
     }
 
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn test_secret_box_zeroize_pages() {
+        let secret_box = SecretBox::new_locked_pages(Box::new([1u8; 32]));
+        let Region { start, len, .. } = secret_box.region;
+        assert!(len >= memory::page_size().unwrap());
+        // Something left behind in the slack of the secret's page
+        let slack = unsafe { std::slice::from_raw_parts_mut(start as *mut u8, len) };
+        slack[len - 1] = 0xAA;
+
+        unsafe { secret_box.zeroize_pages() };
+        let pages = unsafe { std::slice::from_raw_parts(start as *const u8, len) };
+        assert!(pages.iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn test_secret_box_backing_kind() {
+        assert_eq!(
+            SecretBox::new(Box::new([1u8; 32])).backing_kind(),
+            BackingKind::Heap
+        );
+        assert_eq!(
+            SecretBox::new_locked_pages(Box::new([1u8; 32])).backing_kind(),
+            if cfg!(any(unix, windows)) {
+                BackingKind::Pages
+            } else {
+                BackingKind::Heap
+            }
+        );
+        let mut slot = [1u8; 32];
+        let adopted = unsafe { SecretBox::from_locked_ptr(NonNull::from(&mut slot), true) };
+        assert_eq!(adopted.backing_kind(), BackingKind::External);
+    }
+
+    #[cfg(all(target_os = "linux", feature = "memfd-secret"))]
+    #[test]
+    fn test_secret_box_new_memfd_secret() {
+        let secret_box = SecretBox::new_memfd_secret(Box::new([1u8; 32]));
+        let kind = secret_box.backing_kind();
+        assert!(matches!(
+            kind,
+            BackingKind::MemfdSecret | BackingKind::Pages
+        ));
+        assert_eq!(*secret_box.expose_secret(), [1; 32]);
+
+        let cloned = secret_box.clone();
+        assert_eq!(cloned.backing_kind(), kind);
+        assert_eq!(*cloned.expose_secret(), [1; 32]);
+    }
+
+    #[test]
+    fn test_secret_box_drop_verified() {
+        let secret_box = SecretBox::new(Box::new([1u8; 32]));
+        assert_eq!(secret_box.drop_verified(), Ok(()));
+
+        let mut data = Vec::with_capacity(64);
+        data.extend_from_slice(&[1u8; 32]);
+        assert_eq!(SecretVec::from(data).drop_verified(), Ok(()));
+
+        let mut bytes = [0u8; 16];
+        assert_eq!(unsafe { verify_zeroed(bytes.as_ptr(), 16) }, Ok(()));
+        bytes[5] = 1;
+        assert_eq!(
+            unsafe { verify_zeroed(bytes.as_ptr(), 16) },
+            Err(ZeroizeVerifyError { offset: 5 })
+        );
+    }
+
+    #[test]
+    fn test_secret_box_new_with_uninit() {
+        let slot_ptr = Cell::new(ptr::null());
+        let secret_box = unsafe {
+            SecretBox::<[u8; 64]>::new_with_uninit(|slot| {
+                slot_ptr.set(slot.as_ptr());
+                slot.as_mut_ptr().cast::<u8>().write_bytes(1, 64);
+            })
+        };
+        // Initialized right where the secret lives
+        assert_eq!(
+            secret_box.inner_secret.as_ptr().cast_const(),
+            slot_ptr.get()
+        );
+        assert_eq!(*secret_box.expose_secret(), [1; 64]);
+        assert_eq!(secret_box.region.locked, !cfg!(feature = "no-mlock"));
+
+        let result = std::panic::catch_unwind(|| unsafe {
+            SecretBox::<[u8; 64]>::new_with_uninit(|_| panic!("init failed"))
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_secret_box_new_with_ctr_no_intermediate_allocation() {
+        let buffer = Cell::new(ptr::null());
+        let (allocations, live) = (ALLOCATIONS.get(), LIVE_ALLOCATIONS.get());
+
+        let secret_box = SecretBox::new_with_ctr(|| {
+            let secret = TestSecret::new(10);
+            buffer.set(secret.data.as_ptr());
+            secret
+        });
+
+        // Only the secret's own allocation and the `Vec` buffer made by the constructor
+        assert_eq!(ALLOCATIONS.get() - allocations, 2);
+        assert_eq!(LIVE_ALLOCATIONS.get() - live, 2);
+        assert_eq!((*secret_box.expose_secret()).data.as_ptr(), buffer.get());
+    }
+
     #[test]
     fn test_secret_box_try_new_with_ctr() {
         let result: Result<SecretBox<TestSecret>, &'static str> =
@@ -393,6 +3394,329 @@ mod tests {
         }
     }
 
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_secret_box_drop_ignores_unlock_failure() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static DROP_ERRORS: AtomicUsize = AtomicUsize::new(0);
+
+        set_drop_error_hook(|_| {
+            DROP_ERRORS.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let mut secret_box = SecretBox::new(Box::new(TestSecret::new(10)));
+
+        // Point the locked region at pages that are no longer mapped so munlock fails
+        let locked = secret_box.region;
+        let unmapped = unsafe {
+            let page = libc::mmap(
+                ptr::null_mut(),
+                locked.len,
+                libc::PROT_NONE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            );
+            assert_ne!(page, libc::MAP_FAILED);
+            libc::munmap(page, locked.len);
+            page as usize
+        };
+        secret_box.region = Region {
+            start: unmapped,
+            len: locked.len,
+            locked: true,
+            dump_excluded: true,
+        };
+
+        // Dropping the fake region released the real one's budget already
+        drop(secret_box);
+        unsafe { libc::munlock(locked.start as *const libc::c_void, locked.len) };
+
+        assert!(DROP_ERRORS.load(Ordering::SeqCst) > 0);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_try_expose_secret_protection_failure() {
+        let mut secret_box = SecretBox::new_protected(Box::new([1u8; 32]));
+        assert_eq!(*secret_box.try_expose_secret().unwrap(), [1; 32]);
+
+        // Point the protection at a page that is no longer mapped so mprotect fails
+        let page_size = memory::page_size().unwrap();
+        let unmapped = unsafe {
+            let page = libc::mmap(
+                ptr::null_mut(),
+                page_size,
+                libc::PROT_NONE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            );
+            assert_ne!(page, libc::MAP_FAILED);
+            libc::munmap(page, page_size);
+            page as usize
+        };
+        let protection = secret_box.idle_protection.as_mut().unwrap();
+        let start = protection.start;
+        protection.start = unmapped;
+
+        assert!(matches!(
+            secret_box.try_expose_secret(),
+            Err(ExposeError::Protection(SecretBoxError::Mprotect(_)))
+        ));
+        assert!(secret_box.try_expose_secret_mut().is_err());
+
+        secret_box.idle_protection.as_mut().unwrap().start = start;
+        assert_eq!(*secret_box.expose_secret(), [1; 32]);
+    }
+
+    #[test]
+    fn test_secret_vec_locks_buffer() {
+        let secret_vec = SecretVec::from(vec![1u8, 2, 3]);
+        let heap_region = secret_vec.heap_region.expect("Vec buffer is not locked");
+        let ptr = secret_vec.expose_secret().as_ptr() as usize;
+        assert!(heap_region.start <= ptr && ptr + 3 <= heap_region.start + heap_region.len);
+    }
+
+    #[test]
+    fn test_secret_vec_with_bytes() {
+        let secret_vec = SecretVec::from(vec![1u8, 2, 3]);
+        assert_eq!(secret_vec.with_bytes(|bytes| bytes.iter().sum::<u8>()), 6);
+    }
+
+    #[test]
+    fn test_secret_vec_extend_from_secret() {
+        let mut secret_vec = SecretVec::from(Vec::with_capacity(8));
+        secret_vec.extend_from_secret(&SecretVec::from(vec![1u8, 2]));
+        secret_vec.extend_from_secret(&SecretVec::from(vec![3u8; 16]));
+        assert_eq!(secret_vec.expose_secret()[..3], [1, 2, 3]);
+        assert_eq!(secret_vec.expose_secret().len(), 18);
+
+        let heap_region = secret_vec.heap_region.expect("Vec buffer is not locked");
+        let (ptr, len) = secret_vec.expose_secret().region();
+        assert!(heap_region.start <= ptr as usize);
+        assert!(ptr as usize + len <= heap_region.start + heap_region.len);
+
+        // The heap buffer of a plain `SecretBox` isn't locked, so it's moved to one that is
+        let mut secret_box = SecretBox::new(Box::new(Vec::with_capacity(8)));
+        secret_box.extend_from_secret(&SecretVec::from(vec![1u8, 2]));
+        assert_eq!(*secret_box.expose_secret(), [1, 2]);
+        assert!(secret_box.heap_region.is_some());
+    }
+
+    #[test]
+    fn test_secret_vec_reserve_locked() {
+        let mut secret_vec = SecretVec::from(Vec::new());
+        secret_vec.reserve_locked(10);
+        let buffer = secret_vec.expose_secret().as_ptr();
+        assert!(secret_vec.expose_secret().capacity() >= 10);
+        assert!(secret_vec.heap_region.is_some());
+
+        // Pushing into the reserved capacity leaves the buffer in place
+        for i in 0..10 {
+            secret_vec.push_locked(i);
+        }
+        assert_eq!(secret_vec.expose_secret().as_ptr(), buffer);
+
+        secret_vec.extend_locked((10..100).filter(|i| i % 2 == 0));
+        assert_eq!(secret_vec.expose_secret().len(), 55);
+        assert_eq!(secret_vec.expose_secret()[..3], [0, 1, 2]);
+        assert_eq!(secret_vec.expose_secret()[54], 98);
+
+        let heap_region = secret_vec.heap_region.expect("Vec buffer is not locked");
+        let (ptr, len) = secret_vec.expose_secret().region();
+        assert!(heap_region.start <= ptr as usize);
+        assert!(ptr as usize + len <= heap_region.start + heap_region.len);
+    }
+
+    define_secret!(SessionKey([u8; 32]));
+    define_secret!(
+        #[derive(PartialEq)]
+        Password(Vec<u8>);
+    );
+
+    #[test]
+    fn test_define_secret() {
+        let key = SecretBox::new_with_region(Box::new(SessionKey::from([1u8; 32])));
+        assert_eq!(key.clone().expose_secret().0, [1; 32]);
+        assert_eq!(
+            format!("{:?}", *key.expose_secret()),
+            "SessionKey([REDACTED])"
+        );
+
+        let mut password = Password::from(vec![1u8, 2, 3]);
+        assert_eq!(password.region().1, password.0.capacity());
+        password.zeroize();
+        assert_eq!(password, Password(Vec::new()));
+    }
+
+    #[test]
+    fn test_secret_array_from_slice() {
+        let secret_array = SecretArray::<u8, 4>::from_slice(&[1, 2, 3, 4]).unwrap();
+        assert_eq!(*secret_array.expose_secret(), [1, 2, 3, 4]);
+        assert_eq!(
+            SecretArray::<u8, 4>::from_slice(&[1, 2, 3]).unwrap_err(),
+            LenError {
+                expected: 4,
+                actual: 3
+            }
+        );
+        assert_eq!(*SecretArray::<u8, 32>::zeroed().expose_secret(), [0; 32]);
+    }
+
+    #[test]
+    fn test_secret_array_try_from_vec() {
+        let secret_array = SecretArray::<u8, 4>::try_from(vec![1, 2, 3, 4]).unwrap();
+        assert_eq!(*secret_array.expose_secret(), [1, 2, 3, 4]);
+        assert_eq!(
+            SecretArray::<u8, 4>::try_from(vec![1, 2, 3]).unwrap_err(),
+            LenError {
+                expected: 4,
+                actual: 3
+            }
+        );
+    }
+
+    #[test]
+    fn test_secret_slice_from_boxed_slice() {
+        let secret_slice = SecretSlice::from_boxed_slice(vec![1u8; 100].into_boxed_slice());
+        let ptr = secret_slice.expose_secret().as_ptr() as usize;
+        let region = secret_slice.region;
+        assert!(region.start <= ptr && ptr + 100 <= region.start + region.len);
+        assert_eq!(*secret_slice.expose_secret(), [1; 100]);
+        assert_eq!(format!("{:?}", secret_slice), "SecretBox<[u8]>([REDACTED])");
+
+        let empty = SecretSlice::<u8>::from_boxed_slice(Box::new([]));
+        assert_eq!(empty.region.len, 0);
+    }
+
+    #[test]
+    fn test_new_with_region() {
+        let secret_box = SecretBox::new_with_region(Box::new(String::from("Encrypted")));
+        let heap_region = secret_box.heap_region.expect("String buffer is not locked");
+        let ptr = secret_box.expose_secret().as_ptr() as usize;
+        assert!(heap_region.start <= ptr && ptr + 9 <= heap_region.start + heap_region.len);
+
+        let secret_box = SecretBox::new_with_region(Box::new([1u8; 32]));
+        assert!(secret_box.heap_region.is_none());
+    }
+
+    #[test]
+    fn test_new_deep() {
+        let secret_box = SecretBox::new_deep(Box::new(vec![1u64, 2, 3]));
+        assert!(secret_box.heap_region.is_some());
+        assert_eq!(*secret_box.expose_secret(), [1, 2, 3]);
+
+        let secret_box = SecretBox::try_new_deep(Box::new(String::from("Encrypted"))).unwrap();
+        assert!(secret_box.heap_region.is_some());
+    }
+
+    #[test]
+    fn test_secret_vec_with_capacity_locked() {
+        let page_size = memory::page_size().unwrap();
+        let mut secret_vec = SecretVec::<u8>::with_capacity_locked(page_size * 2);
+        let heap_region = secret_vec.heap_region.expect("Vec buffer is not locked");
+        let (ptr, capacity) = secret_vec.with_exposed(|vec| (vec.as_ptr(), vec.capacity()));
+        assert!(secret_vec.expose_secret().is_empty());
+        assert!(capacity >= page_size * 2);
+        assert!(heap_region.start <= ptr as usize);
+        assert!(ptr as usize + capacity <= heap_region.start + heap_region.len);
+        let spare = unsafe { std::slice::from_raw_parts(ptr, capacity) };
+        assert!(spare.iter().all(|&byte| byte == 0));
+
+        // Filling the capacity keeps the buffer and its lock
+        secret_vec
+            .expose_secret_mut()
+            .extend(std::iter::repeat_n(1, page_size * 2));
+        assert_eq!(secret_vec.expose_secret().as_ptr(), ptr);
+        let refilled = secret_vec.heap_region.expect("Vec buffer is not locked");
+        assert_eq!(
+            (refilled.start, refilled.len),
+            (heap_region.start, heap_region.len)
+        );
+    }
+
+    #[test]
+    fn test_secret_vec_new_with_len() {
+        let secret_vec = SecretVec::<u8>::new_with_len(100);
+        assert_eq!(*secret_vec.expose_secret(), [0; 100]);
+        assert!(secret_vec.heap_region.is_some());
+    }
+
+    #[test]
+    fn test_secret_vec_from_slice_zeroizing() {
+        let mut src = [1u8, 2, 3, 4];
+        let secret_vec = SecretVec::from_slice_zeroizing(&mut src);
+        assert_eq!(*secret_vec.expose_secret(), [1, 2, 3, 4]);
+        assert_eq!(src, [0; 4]);
+    }
+
+    #[test]
+    fn test_secret_vec_relocks_grown_buffer() {
+        let mut secret_vec = SecretVec::from(vec![1u8; 16]);
+        let page_size = memory::page_size().unwrap();
+        secret_vec
+            .expose_secret_mut()
+            .extend(std::iter::repeat_n(2, page_size * 4));
+
+        let heap_region = secret_vec.heap_region.expect("Vec buffer is not locked");
+        let guard = secret_vec.expose_secret();
+        let (ptr, len) = (guard.as_ptr() as usize, guard.capacity());
+        assert!(heap_region.start <= ptr && ptr + len <= heap_region.start + heap_region.len);
+    }
+
+    #[test]
+    fn test_secret_box_relocks_shrunk_string() {
+        let page_size = memory::page_size().unwrap();
+        let mut secret_box = SecretBox::new_with_region(Box::new("a".repeat(page_size * 4)));
+        let old_region = secret_box.heap_region.expect("String buffer is not locked");
+
+        {
+            let mut guard = secret_box.expose_secret_mut();
+            guard.truncate(16);
+            guard.shrink_to_fit();
+        }
+        // Even if the buffer shrank in place, only its remaining pages stay locked
+        let heap_region = secret_box.heap_region.expect("String buffer is not locked");
+        let (ptr, len) = secret_box.expose_secret().region();
+        assert!(heap_region.start <= ptr as usize);
+        assert!(ptr as usize + len <= heap_region.start + heap_region.len);
+        assert!(heap_region.len < old_region.len);
+    }
+
+    #[test]
+    fn test_secret_box_relocks_assigned_string() {
+        let mut secret_box = SecretBox::new_with_region(Box::new(String::from("short")));
+
+        let page_size = memory::page_size().unwrap();
+        *secret_box.expose_secret_mut() = "much longer secret".repeat(page_size);
+        let heap_region = secret_box.heap_region.expect("String buffer is not locked");
+        let (ptr, len) = secret_box.expose_secret().region();
+        assert!(heap_region.start <= ptr as usize);
+        assert!(ptr as usize + len <= heap_region.start + heap_region.len);
+
+        let cloned = secret_box.clone();
+        assert!(cloned.heap_region.is_some());
+
+        *secret_box.expose_secret_mut() = String::new();
+        assert!(secret_box.heap_region.is_none());
+    }
+
+    #[test]
+    fn test_dump_protection_available() {
+        assert_eq!(
+            dump_protection_available(),
+            cfg!(any(
+                target_os = "linux",
+                target_os = "android",
+                target_os = "freebsd",
+                target_os = "dragonfly"
+            ))
+        );
+    }
+
+    #[cfg(feature = "guard-eq")]
     #[test]
     fn test_secret_guard_equality() {
         let secret_guard_a = SecretGuard::new(&5);
@@ -0,0 +1,178 @@
+//! A map of named secrets, e.g. a credential store.
+
+use core::fmt::{self, Debug};
+use std::{borrow::Borrow, collections::HashMap, hash::Hash};
+
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::{ExposeSecret, SecretBox};
+
+/// Map from keys to locked secrets, e.g. credentials looked up by name.
+///
+/// Every value is a [`SecretBox`] living in its own locked allocation. Growing the map only
+/// moves the boxes' handles, so rehashing never copies a secret into unlocked memory. The
+/// keys aren't secret and aren't locked, but they're zeroized when they're removed, on
+/// [`Self::clear`] and on drop. Growing the map can still leave copies of keys in freed
+/// memory, use [`Self::with_capacity`] if the keys are sensitive too.
+///
+/// ```
+/// use shush_rs::{SecretBox, SecretMap};
+///
+/// let mut credentials = SecretMap::new();
+/// credentials.insert(String::from("db"), SecretBox::new(Box::new(vec![1u8, 2, 3])));
+/// assert_eq!(credentials.get_with("db", |password| password.len()), Some(3));
+/// credentials.clear();
+/// assert!(credentials.is_empty());
+/// ```
+pub struct SecretMap<K: Zeroize + Eq + Hash, V: Zeroize> {
+    entries: HashMap<K, SecretBox<V>>,
+}
+
+impl<K: Zeroize + Eq + Hash, V: Zeroize> SecretMap<K, V> {
+    /// Create an empty map.
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Create an empty map with room for `capacity` secrets before it has to grow.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::with_capacity(capacity),
+        }
+    }
+
+    /// Number of secrets in the map.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the map holds no secrets.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Whether the map holds a secret for `key`.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.entries.contains_key(key)
+    }
+
+    /// Store `secret` under `key`, returning the secret previously stored under it.
+    ///
+    /// If the key is already present, the passed `key` is zeroized and the stored one kept.
+    pub fn insert(&mut self, mut key: K, secret: SecretBox<V>) -> Option<SecretBox<V>> {
+        match self.entries.get_mut(&key) {
+            Some(stored) => {
+                key.zeroize();
+                Some(std::mem::replace(stored, secret))
+            }
+            None => {
+                self.entries.insert(key, secret);
+                None
+            }
+        }
+    }
+
+    /// Expose the secret stored under `key` for the duration of `f`, see
+    /// [`SecretBox::with_exposed`]. Returns `None` if there is no such secret.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret can't be made accessible, see [`ExposeSecret::expose_secret`].
+    pub fn get_with<Q, R>(&self, key: &Q, f: impl FnOnce(&V) -> R) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.entries
+            .get(key)
+            .map(|secret| f(&secret.expose_secret()))
+    }
+
+    /// Remove the secret stored under `key` and zeroize the stored key.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<SecretBox<V>>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.entries.remove_entry(key).map(|(mut key, secret)| {
+            key.zeroize();
+            secret
+        })
+    }
+
+    /// Zeroize and drop all keys and secrets. The map keeps its capacity.
+    pub fn clear(&mut self) {
+        for (mut key, secret) in self.entries.drain() {
+            key.zeroize();
+            drop(secret);
+        }
+    }
+}
+
+impl<K: Zeroize + Eq + Hash, V: Zeroize> Default for SecretMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Zeroize + Eq + Hash, V: Zeroize> Zeroize for SecretMap<K, V> {
+    fn zeroize(&mut self) {
+        self.clear()
+    }
+}
+
+impl<K: Zeroize + Eq + Hash, V: Zeroize> Drop for SecretMap<K, V> {
+    fn drop(&mut self) {
+        self.clear()
+    }
+}
+
+impl<K: Zeroize + Eq + Hash, V: Zeroize> ZeroizeOnDrop for SecretMap<K, V> {}
+
+impl<K: Zeroize + Eq + Hash, V: Zeroize> Debug for SecretMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretMap({} x [REDACTED])", self.entries.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_map() {
+        let mut map = SecretMap::new();
+        assert!(map
+            .insert(String::from("a"), SecretBox::new(Box::new([1u8; 32])))
+            .is_none());
+        let secret = SecretBox::new(Box::new([2u8; 32]));
+        let secret_ptr = secret.inner_secret;
+        assert!(map.insert(String::from("b"), secret).is_none());
+
+        // Growing the map must leave the secrets where they are
+        for i in 0..100 {
+            map.insert(i.to_string(), SecretBox::new(Box::new([0u8; 32])));
+        }
+        assert_eq!(map.entries["b"].inner_secret, secret_ptr);
+        assert_eq!(map.get_with("b", |secret| secret[0]), Some(2));
+        assert_eq!(map.get_with("c", |secret| secret[0]), None);
+
+        let replaced = map.insert(String::from("a"), SecretBox::new(Box::new([3u8; 32])));
+        assert_eq!(*replaced.unwrap().expose_secret(), [1; 32]);
+        assert_eq!(map.get_with("a", |secret| secret[0]), Some(3));
+
+        assert!(map.remove("a").is_some());
+        assert!(!map.contains_key("a"));
+        assert_eq!(map.len(), 101);
+        assert_eq!(format!("{:?}", map), "SecretMap(101 x [REDACTED])");
+
+        map.clear();
+        assert!(map.is_empty());
+    }
+}
@@ -0,0 +1,206 @@
+//! Values that may or may not be secret, decided at runtime.
+
+use core::fmt::{self, Debug};
+use std::ops::Deref;
+
+use zeroize::Zeroize;
+
+use crate::{CloneableSecret, ExposeSecret, SecretBox, SecretGuard};
+
+/// Either a plain value or a [`SecretBox`], e.g. a config field that is only sensitive for
+/// some keys.
+///
+/// Both are read the same way, through [`Self::expose`] or [`Self::with`], so generic code
+/// doesn't have to branch, while only secret values pay for locking their memory. `Debug`
+/// prints plain values and redacts secret ones.
+///
+/// ```
+/// use shush_rs::MaybeSecret;
+///
+/// let fields: Vec<(&str, MaybeSecret<String>)> = ["host=db.local", "password=hunter2"]
+///     .iter()
+///     .map(|field| {
+///         let (key, value) = field.split_once('=').unwrap();
+///         let value = if key == "password" {
+///             MaybeSecret::secret(value.to_owned())
+///         } else {
+///             MaybeSecret::plain(value.to_owned())
+///         };
+///         (key, value)
+///     })
+///     .collect();
+///
+/// assert_eq!(fields[1].1.with(|password| password.len()), 7);
+/// assert_eq!(format!("{:?}", fields[0].1), r#"Plain("db.local")"#);
+/// assert_eq!(
+///     format!("{:?}", fields[1].1),
+///     "Secret(SecretBox<alloc::string::String>([REDACTED]))"
+/// );
+/// ```
+pub enum MaybeSecret<S: Zeroize> {
+    /// A value that isn't sensitive, kept as is.
+    Plain(S),
+    /// A sensitive value, locked and zeroized on drop.
+    Secret(SecretBox<S>),
+}
+
+/// Exposes the value of a [`MaybeSecret`], returned by [`MaybeSecret::expose`].
+pub struct MaybeSecretGuard<'a, S: Zeroize> {
+    exposed: Exposed<'a, S>,
+}
+
+enum Exposed<'a, S: Zeroize> {
+    Plain(&'a S),
+    Secret(SecretGuard<'a, S>),
+}
+
+impl<S: Zeroize> MaybeSecret<S> {
+    /// Keep `value` as a plain value.
+    pub fn plain(value: S) -> Self {
+        MaybeSecret::Plain(value)
+    }
+
+    /// Move `value` into a [`SecretBox`], see [`SecretBox::new`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret's memory can't be locked.
+    pub fn secret(value: S) -> Self {
+        MaybeSecret::Secret(SecretBox::new(Box::new(value)))
+    }
+
+    /// Whether the value is secret.
+    pub fn is_secret(&self) -> bool {
+        matches!(self, MaybeSecret::Secret(_))
+    }
+
+    /// Expose the value until the guard is dropped, see [`ExposeSecret::expose_secret`] for
+    /// secret values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a secret value can't be made accessible.
+    pub fn expose(&self) -> MaybeSecretGuard<'_, S> {
+        let exposed = match self {
+            MaybeSecret::Plain(value) => Exposed::Plain(value),
+            MaybeSecret::Secret(secret) => Exposed::Secret(secret.expose_secret()),
+        };
+        MaybeSecretGuard { exposed }
+    }
+
+    /// Expose the value only for the duration of `f`, see [`SecretBox::with_exposed`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if a secret value can't be made accessible.
+    pub fn with<R>(&self, f: impl FnOnce(&S) -> R) -> R {
+        match self {
+            MaybeSecret::Plain(value) => f(value),
+            MaybeSecret::Secret(secret) => secret.with_exposed(f),
+        }
+    }
+
+    /// Expose the value mutably only for the duration of `f`, see
+    /// [`SecretBox::with_exposed_mut`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if a secret value can't be made accessible.
+    pub fn with_mut<R>(&mut self, f: impl FnOnce(&mut S) -> R) -> R {
+        match self {
+            MaybeSecret::Plain(value) => f(value),
+            MaybeSecret::Secret(secret) => secret.with_exposed_mut(f),
+        }
+    }
+
+    /// Turn the value into a secret, locking a plain value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a plain value's memory can't be locked.
+    pub fn into_secret(self) -> SecretBox<S> {
+        match self {
+            MaybeSecret::Plain(value) => SecretBox::new(Box::new(value)),
+            MaybeSecret::Secret(secret) => secret,
+        }
+    }
+
+    /// Take the value out, unlocking a secret one, see [`SecretBox::into_inner`].
+    pub fn into_inner(self) -> S {
+        match self {
+            MaybeSecret::Plain(value) => value,
+            MaybeSecret::Secret(secret) => *secret.into_inner(),
+        }
+    }
+}
+
+impl<S: Zeroize> From<SecretBox<S>> for MaybeSecret<S> {
+    fn from(secret: SecretBox<S>) -> Self {
+        MaybeSecret::Secret(secret)
+    }
+}
+
+impl<S: CloneableSecret> Clone for MaybeSecret<S> {
+    /// Clone the value, a secret one into new locked memory.
+    fn clone(&self) -> Self {
+        match self {
+            MaybeSecret::Plain(value) => MaybeSecret::Plain(value.clone()),
+            MaybeSecret::Secret(secret) => MaybeSecret::Secret(secret.clone()),
+        }
+    }
+}
+
+impl<S: Zeroize + Debug> Debug for MaybeSecret<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaybeSecret::Plain(value) => f.debug_tuple("Plain").field(value).finish(),
+            MaybeSecret::Secret(secret) => f.debug_tuple("Secret").field(secret).finish(),
+        }
+    }
+}
+
+impl<S: Zeroize> Deref for MaybeSecretGuard<'_, S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        match &self.exposed {
+            Exposed::Plain(value) => value,
+            Exposed::Secret(guard) => guard,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_maybe_secret() {
+        let mut plain = MaybeSecret::plain([1u8; 32]);
+        let mut secret = MaybeSecret::secret([1u8; 32]);
+        assert!(!plain.is_secret());
+        assert!(secret.is_secret());
+
+        for value in [&mut plain, &mut secret] {
+            assert_eq!(*value.expose(), [1; 32]);
+            value.with_mut(|bytes| bytes[0] = 2);
+            assert_eq!(value.with(|bytes| bytes[0]), 2);
+        }
+
+        let mut expected = [1u8; 32];
+        expected[0] = 2;
+        assert_eq!(
+            format!("{:?}", plain.clone()),
+            format!("Plain({:?})", expected)
+        );
+        assert_eq!(
+            format!("{:?}", secret.clone()),
+            "Secret(SecretBox<[u8; 32]>([REDACTED]))"
+        );
+
+        let locked = plain.into_secret();
+        assert_eq!(locked.expose_secret()[0], 2);
+        assert_eq!(MaybeSecret::from(locked).into_inner()[0], 2);
+        assert_eq!(secret.into_inner()[0], 2);
+    }
+}
@@ -0,0 +1,926 @@
+//! Platform specific memory locking and protection.
+
+#[cfg(unix)]
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+#[cfg(any(unix, windows))]
+use std::sync::OnceLock;
+
+#[cfg(any(unix, windows))]
+use errno::{errno, Errno};
+
+#[cfg(unix)]
+use libc::{mlock, munlock, sysconf, _SC_PAGESIZE};
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "dragonfly"
+))]
+use libc::madvise;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use libc::{MADV_DODUMP, MADV_DONTDUMP};
+
+#[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+use libc::{MADV_CORE as MADV_DODUMP, MADV_NOCORE as MADV_DONTDUMP};
+
+/// `INHERIT_ZERO` value of `minherit(2)`: the child process gets zeroed pages instead of a
+/// copy. Not exposed by the `libc` crate.
+#[cfg(any(target_os = "openbsd", target_os = "freebsd"))]
+const INHERIT_ZERO: libc::c_int = 3;
+#[cfg(target_os = "netbsd")]
+const INHERIT_ZERO: libc::c_int = 5;
+
+#[cfg(any(target_os = "openbsd", target_os = "freebsd", target_os = "netbsd"))]
+extern "C" {
+    fn minherit(addr: *mut c_void, len: libc::size_t, inherit: libc::c_int) -> libc::c_int;
+}
+
+#[cfg(target_os = "linux")]
+use crate::ResidencyError;
+use crate::{observer::notify, SecretBoxBuilder, SecretBoxError};
+
+/// Page size looked up by the first successful [`page_size`] call.
+#[cfg(any(unix, windows))]
+static PAGE_SIZE: OnceLock<usize> = OnceLock::new();
+
+/// Get the system's page size.
+///
+/// The page size is only looked up once. A failed lookup isn't cached, it's returned to the
+/// caller and retried by the next call. So is a page size that isn't a power of two, which
+/// would break the masks aligning secrets to their pages.
+pub(crate) fn page_size() -> Result<usize, SecretBoxError> {
+    #[cfg(any(unix, windows))]
+    if let Some(&page_size) = PAGE_SIZE.get() {
+        return Ok(page_size);
+    }
+
+    #[cfg(unix)]
+    {
+        let page_size = unsafe { sysconf(_SC_PAGESIZE) };
+        if page_size == -1 {
+            return Err(SecretBoxError::PageSize(errno()));
+        }
+        let page_size = checked_page_size(page_size as usize)?;
+        Ok(*PAGE_SIZE.get_or_init(|| page_size))
+    }
+
+    #[cfg(windows)]
+    {
+        let mut info = unsafe { std::mem::zeroed() };
+        unsafe { windows_sys::Win32::System::SystemInformation::GetSystemInfo(&mut info) };
+        let page_size = checked_page_size(info.dwPageSize as usize)?;
+        Ok(*PAGE_SIZE.get_or_init(|| page_size))
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    Err(SecretBoxError::Unsupported)
+}
+
+/// Reject page sizes [`aligned_region`] can't align to.
+#[cfg_attr(not(any(unix, windows)), allow(dead_code))]
+fn checked_page_size(page_size: usize) -> Result<usize, SecretBoxError> {
+    if page_size.is_power_of_two() {
+        Ok(page_size)
+    } else {
+        Err(SecretBoxError::InvalidPageSize(page_size))
+    }
+}
+
+/// Whether secrets are excluded from core dumps on this platform.
+///
+/// This is the case on Linux (`MADV_DONTDUMP`) and on FreeBSD and DragonFly BSD
+/// (`MADV_NOCORE`). Other platforms have no way to exclude single pages from a core dump,
+/// see [`disable_core_dumps`] for a process-wide alternative.
+pub fn dump_protection_available() -> bool {
+    cfg!(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "dragonfly"
+    ))
+}
+
+/// Disable core dumps for the whole process by setting `RLIMIT_CORE` to zero.
+///
+/// This is the only way to keep secrets out of core dumps on platforms without
+/// [`dump_protection_available`], like macOS. Since it affects the entire process and can't
+/// be undone without privileges, the crate never does this on its own.
+#[cfg(unix)]
+pub fn disable_core_dumps() -> Result<(), Errno> {
+    let limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    if unsafe { libc::setrlimit(libc::RLIMIT_CORE, &limit) } != 0 {
+        return Err(errno());
+    }
+    Ok(())
+}
+
+/// Memory region protected for a secret.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(not(any(unix, windows)), allow(dead_code))]
+pub(crate) struct Region {
+    pub(crate) start: usize,
+    pub(crate) len: usize,
+    /// Whether the region is actually locked, see
+    /// [`SecretBox::new_best_effort`](crate::SecretBox::new_best_effort).
+    pub(crate) locked: bool,
+    /// Whether the region is excluded from core dumps, see
+    /// [`SecretBoxBuilder::dump_protect`](crate::SecretBoxBuilder::dump_protect).
+    pub(crate) dump_excluded: bool,
+}
+
+/// Set by [`disable_memory_locking`].
+static LOCKING_DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Stop locking the memory of secrets created from now on, the runtime counterpart of the
+/// `no-mlock` feature. Secrets are still excluded from core dumps where possible and
+/// zeroized on drop.
+///
+/// Meant for sandboxes that don't allow `mlock` but kill the process instead of failing the
+/// call, above all OpenBSD's `pledge(2)`, where `mlock` may be missing from the promises a
+/// program pledges. Call it before the first secret is created, secrets locked earlier still
+/// call `munlock` when they're dropped.
+///
+/// ```no_run
+/// # fn pledge(_: &str) {}
+/// shush_rs::disable_memory_locking();
+/// pledge("stdio");
+/// let key = shush_rs::SecretBox::new(Box::new([0u8; 32]));
+/// ```
+pub fn disable_memory_locking() {
+    LOCKING_DISABLED.store(true, Ordering::Relaxed);
+}
+
+/// Soft cap on the bytes locked by all secrets together, see [`set_memlock_budget`].
+static MEMLOCK_BUDGET: MemlockBudget = MemlockBudget::new();
+
+/// Limit the bytes all secrets together may lock to `bytes`, e.g. to stay well below
+/// `RLIMIT_MEMLOCK` and leave room for other users of locked memory in the process.
+///
+/// Locks that would exceed the budget fail with [`SecretBoxError::MemlockBudget`] before
+/// `mlock` is even called, constructors like [`SecretBox::new_best_effort`] leave the secret
+/// unlocked instead. Locked bytes are counted in whole pages like
+/// [`SecretBox::locked_bytes`], a page shared by two locks counts twice. Secrets locked
+/// before the budget was set count against it as well. The budget applies process-wide and
+/// is unlimited by default, `usize::MAX` lifts it again.
+///
+/// [`SecretBox::new_best_effort`]: crate::SecretBox::new_best_effort
+/// [`SecretBox::locked_bytes`]: crate::SecretBox::locked_bytes
+pub fn set_memlock_budget(bytes: usize) {
+    MEMLOCK_BUDGET.cap.store(bytes, Ordering::Relaxed);
+}
+
+/// Bytes locked against a cap, updated without locks so a secret can be created or dropped
+/// from anywhere, e.g. a signal handler's thread, without waiting on another.
+#[cfg_attr(not(any(unix, windows)), allow(dead_code))]
+struct MemlockBudget {
+    locked: AtomicUsize,
+    cap: AtomicUsize,
+}
+
+#[cfg_attr(not(any(unix, windows)), allow(dead_code))]
+impl MemlockBudget {
+    const fn new() -> Self {
+        Self {
+            locked: AtomicUsize::new(0),
+            cap: AtomicUsize::new(usize::MAX),
+        }
+    }
+
+    /// Count `len` more locked bytes, unless they would exceed the cap.
+    fn reserve(&self, len: usize) -> Result<(), SecretBoxError> {
+        let budget = self.cap.load(Ordering::Relaxed);
+        self.locked
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |locked| {
+                locked.checked_add(len).filter(|&total| total <= budget)
+            })
+            .map(drop)
+            .map_err(|_| SecretBoxError::MemlockBudget {
+                requested: len,
+                budget,
+            })
+    }
+
+    /// Count `len` bytes as unlocked again.
+    fn release(&self, len: usize) {
+        self.locked.fetch_sub(len, Ordering::Relaxed);
+    }
+}
+
+/// How [`lock_memory`] deals with a failing lock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LockMode {
+    /// Locking failures are returned as errors.
+    Required,
+    /// The memory is left unlocked when the locked-memory limit is reached.
+    BestEffort,
+    /// The memory isn't locked at all, it's still excluded from core dumps.
+    Skip,
+}
+
+/// Lock the pages spanning `len` bytes at `ptr` and exclude them from core dumps, as far as
+/// `policy` asks for it.
+pub(crate) unsafe fn lock_memory(
+    ptr: *const u8,
+    len: usize,
+    policy: SecretBoxBuilder,
+) -> Result<Region, SecretBoxError> {
+    let result = lock_region(ptr, len, policy);
+    match &result {
+        Ok(region) if region.locked => notify(|observer| observer.on_lock(region.len)),
+        Ok(_) => {}
+        Err(err) => notify(|observer| observer.on_lock_error(err)),
+    }
+    result
+}
+
+#[cfg_attr(not(unix), allow(unused_variables))]
+unsafe fn lock_region(
+    ptr: *const u8,
+    len: usize,
+    policy: SecretBoxBuilder,
+) -> Result<Region, SecretBoxError> {
+    let mode = if cfg!(feature = "no-mlock") || LOCKING_DISABLED.load(Ordering::Relaxed) {
+        LockMode::Skip
+    } else if cfg!(any(target_os = "android", target_os = "ios"))
+        && policy.lock == LockMode::Required
+    {
+        // Apps rarely get the privileges or limits `mlock` needs on mobile platforms, a
+        // failed lock leaves the secret usable instead of making every constructor panic
+        LockMode::BestEffort
+    } else {
+        policy.lock
+    };
+
+    // Zero-sized secrets have no memory to protect, and a zero length is rejected by some
+    // platforms' `mlock`
+    if len == 0 {
+        return Ok(Region {
+            start: ptr as usize,
+            len: 0,
+            locked: false,
+            dump_excluded: false,
+        });
+    }
+
+    #[cfg(unix)]
+    {
+        let page_size = page_size()?;
+        let (start, aligned_len) = aligned_region(ptr as usize, len, page_size)?;
+
+        let dump_excluded = policy.dump_protect && dump_protection_available();
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "dragonfly"
+        ))]
+        if dump_excluded && madvise(start as *mut c_void, aligned_len, MADV_DONTDUMP) != 0 {
+            return Err(SecretBoxError::Madvise(errno()));
+        }
+        let mut locked = mode != LockMode::Skip;
+        if locked {
+            if let Err(err) = MEMLOCK_BUDGET.reserve(aligned_len) {
+                if mode == LockMode::Required {
+                    #[cfg(any(
+                        target_os = "linux",
+                        target_os = "android",
+                        target_os = "freebsd",
+                        target_os = "dragonfly"
+                    ))]
+                    if dump_excluded {
+                        madvise(start as *mut c_void, aligned_len, MADV_DODUMP);
+                    }
+                    return Err(err);
+                }
+                notify(|observer| observer.on_lock_error(&err));
+                locked = false;
+            }
+        }
+        if let Some(Err(errno)) =
+            locked.then(|| mlock_chunked(start, aligned_len, page_size, policy.lock_on_fault))
+        {
+            MEMLOCK_BUDGET.release(aligned_len);
+            if mode == LockMode::Required || !is_lock_limit(errno) {
+                #[cfg(any(
+                    target_os = "linux",
+                    target_os = "android",
+                    target_os = "freebsd",
+                    target_os = "dragonfly"
+                ))]
+                if dump_excluded {
+                    madvise(start as *mut c_void, aligned_len, MADV_DODUMP);
+                }
+                return Err(SecretBoxError::Mlock(errno));
+            }
+            let err = SecretBoxError::Mlock(errno);
+            notify(|observer| observer.on_lock_error(&err));
+            locked = false;
+        }
+
+        Ok(Region {
+            start,
+            len: aligned_len,
+            locked,
+            dump_excluded,
+        })
+    }
+
+    #[cfg(windows)]
+    {
+        checked_region_len(len)?;
+        let mut locked = mode != LockMode::Skip;
+        if locked {
+            if let Err(err) = MEMLOCK_BUDGET.reserve(len) {
+                if mode == LockMode::Required {
+                    return Err(err);
+                }
+                notify(|observer| observer.on_lock_error(&err));
+                locked = false;
+            }
+        }
+        if locked && windows_sys::Win32::System::Memory::VirtualLock(ptr.cast(), len) == 0 {
+            MEMLOCK_BUDGET.release(len);
+            let errno = errno();
+            if mode == LockMode::Required || !is_lock_limit(errno) {
+                return Err(SecretBoxError::Mlock(errno));
+            }
+            let err = SecretBoxError::Mlock(errno);
+            notify(|observer| observer.on_lock_error(&err));
+            locked = false;
+        }
+
+        Ok(Region {
+            start: ptr as usize,
+            len,
+            locked,
+            dump_excluded: false,
+        })
+    }
+
+    // Nothing to lock on targets like wasm32, secrets are only zeroized on drop
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = mode;
+        Ok(Region {
+            start: ptr as usize,
+            len,
+            locked: false,
+            dump_excluded: false,
+        })
+    }
+}
+
+/// Exclude a region returned by [`lock_memory`] from core dumps again.
+///
+/// The flag set by `madvise` can be reset behind the secret's back, e.g. by another
+/// `madvise` call on overlapping pages, so it's reasserted whenever the secret is exposed.
+/// A no-op unless the region was excluded from core dumps in the first place.
+#[cfg_attr(
+    not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "dragonfly"
+    )),
+    allow(unused_variables)
+)]
+pub(crate) unsafe fn reassert_dump_exclusion(region: Region) -> Result<(), SecretBoxError> {
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "dragonfly"
+    ))]
+    if region.dump_excluded && madvise(region.start as *mut c_void, region.len, MADV_DONTDUMP) != 0
+    {
+        return Err(SecretBoxError::Madvise(errno()));
+    }
+    Ok(())
+}
+
+/// Lock a region returned by [`lock_memory`] again, e.g. after unlocking pages it shares
+/// with another region, and exclude it from core dumps again.
+///
+/// The region is already accounted for, so unlike [`lock_memory`] this neither reserves
+/// from the [`set_memlock_budget`] budget nor notifies the observer.
+#[cfg_attr(not(unix), allow(unused_variables))]
+pub(crate) unsafe fn relock_region(region: Region, on_fault: bool) -> Result<(), SecretBoxError> {
+    reassert_dump_exclusion(region)?;
+    if !region.locked {
+        return Ok(());
+    }
+
+    #[cfg(unix)]
+    if let Err(errno) = mlock_chunked(region.start, region.len, page_size()?, on_fault) {
+        return Err(SecretBoxError::Mlock(errno));
+    }
+
+    #[cfg(windows)]
+    if windows_sys::Win32::System::Memory::VirtualLock(region.start as *const _, region.len) == 0 {
+        return Err(SecretBoxError::Mlock(errno()));
+    }
+
+    Ok(())
+}
+
+/// Lock the `len` bytes of pages at `start` one page at a time.
+///
+/// If a page can't be locked, the pages locked before it are unlocked again, so a failure
+/// never leaves a large secret partly locked.
+#[cfg(unix)]
+unsafe fn mlock_chunked(
+    start: usize,
+    len: usize,
+    page_size: usize,
+    on_fault: bool,
+) -> Result<(), Errno> {
+    for offset in (0..len).step_by(page_size) {
+        if lock_page((start + offset) as *const c_void, page_size, on_fault) != 0 {
+            let errno = errno();
+            if offset != 0 {
+                munlock(start as *const c_void, offset);
+            }
+            return Err(errno);
+        }
+    }
+    Ok(())
+}
+
+/// Set once `mlock2` turned out to be missing, so it isn't tried for every page.
+#[cfg(target_os = "linux")]
+static NO_MLOCK2: AtomicBool = AtomicBool::new(false);
+
+/// Lock `len` bytes at `addr`, with `MLOCK_ONFAULT` if `on_fault` is set and the kernel
+/// supports it (Linux 4.4+). Returns `mlock`'s result.
+#[cfg(unix)]
+#[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+unsafe fn lock_page(addr: *const c_void, len: usize, on_fault: bool) -> i32 {
+    #[cfg(target_os = "linux")]
+    if on_fault && !NO_MLOCK2.load(Ordering::Relaxed) {
+        let result = libc::syscall(libc::SYS_mlock2, addr, len, libc::MLOCK_ONFAULT);
+        if result == 0 || errno().0 != libc::ENOSYS {
+            return result as i32;
+        }
+        NO_MLOCK2.store(true, Ordering::Relaxed);
+    }
+    mlock(addr, len)
+}
+
+/// Align the `len` bytes at `addr` to page boundaries, returning the start and length of the
+/// pages spanning them. An empty range spans no pages. `page_size` must be a power of two,
+/// see [`checked_page_size`].
+///
+/// No allocation can be larger than `isize::MAX` bytes, so a longer range can't belong to a
+/// secret. Such lengths are rejected rather than passed on to `mlock` or `madvise`.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn aligned_region(
+    addr: usize,
+    len: usize,
+    page_size: usize,
+) -> Result<(usize, usize), SecretBoxError> {
+    debug_assert!(page_size.is_power_of_two());
+    let start = addr & !(page_size - 1);
+    if len == 0 {
+        return Ok((start, 0));
+    }
+    let end = addr
+        .checked_add(len)
+        .and_then(|end| end.checked_add(page_size - 1))
+        .ok_or(SecretBoxError::RegionOverflow)?
+        & !(page_size - 1);
+    checked_region_len(end - start).map(|len| (start, len))
+}
+
+/// Reject region lengths beyond `isize::MAX`, see [`aligned_region`].
+#[cfg_attr(not(any(unix, windows)), allow(dead_code))]
+fn checked_region_len(len: usize) -> Result<usize, SecretBoxError> {
+    if len > isize::MAX as usize {
+        return Err(SecretBoxError::RegionOverflow);
+    }
+    Ok(len)
+}
+
+/// Whether a failed lock was caused by the process' locked-memory limit.
+#[cfg(unix)]
+fn is_lock_limit(errno: Errno) -> bool {
+    matches!(errno.0, libc::ENOMEM | libc::EAGAIN | libc::EPERM)
+}
+
+/// Whether a failed lock was caused by the process' locked-memory limit.
+#[cfg(windows)]
+fn is_lock_limit(errno: Errno) -> bool {
+    use windows_sys::Win32::Foundation::{
+        ERROR_NOT_ENOUGH_MEMORY, ERROR_NO_SYSTEM_RESOURCES, ERROR_WORKING_SET_QUOTA,
+    };
+
+    matches!(
+        errno.0 as u32,
+        ERROR_NOT_ENOUGH_MEMORY | ERROR_NO_SYSTEM_RESOURCES | ERROR_WORKING_SET_QUOTA
+    )
+}
+
+/// Undo [`lock_memory`] for `region`, collecting the errors of every step that failed.
+#[cfg_attr(not(any(unix, windows)), allow(unused_mut, unused_variables))]
+pub(crate) unsafe fn unlock_memory(region: Region) -> Vec<SecretBoxError> {
+    let mut errors = Vec::new();
+    if region.len == 0 {
+        return errors;
+    }
+
+    #[cfg(unix)]
+    {
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "dragonfly"
+        ))]
+        if region.dump_excluded
+            && madvise(region.start as *mut c_void, region.len, MADV_DODUMP) != 0
+        {
+            errors.push(SecretBoxError::Madvise(errno()));
+        }
+
+        if region.locked {
+            MEMLOCK_BUDGET.release(region.len);
+            if munlock(region.start as *const c_void, region.len) != 0 {
+                errors.push(SecretBoxError::Munlock(errno()));
+            } else {
+                notify(|observer| observer.on_unlock(region.len));
+            }
+        }
+    }
+
+    #[cfg(windows)]
+    if region.locked {
+        MEMLOCK_BUDGET.release(region.len);
+        if windows_sys::Win32::System::Memory::VirtualUnlock(region.start as *const _, region.len)
+            == 0
+        {
+            errors.push(SecretBoxError::Munlock(errno()));
+        } else {
+            notify(|observer| observer.on_unlock(region.len));
+        }
+    }
+
+    errors
+}
+
+/// Access allowed to a range of pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Access {
+    None,
+    ReadOnly,
+    ReadWrite,
+}
+
+/// Map `len` bytes of read-write memory with `memfd_secret(2)`, which removes the pages from
+/// the kernel's direct map so neither the kernel nor other processes can read them. Returns
+/// `None` where the kernel lacks it (before Linux 5.14, or without `secretmem.enable`).
+pub(crate) fn map_secret_pages(len: usize) -> Result<Option<usize>, SecretBoxError> {
+    #[cfg(all(target_os = "linux", feature = "memfd-secret"))]
+    unsafe {
+        let fd = libc::syscall(libc::SYS_memfd_secret, libc::O_CLOEXEC);
+        if fd < 0 {
+            let errno = errno();
+            if errno.0 == libc::ENOSYS {
+                return Ok(None);
+            }
+            return Err(SecretBoxError::Mmap(errno));
+        }
+        let fd = fd as libc::c_int;
+        let result = if libc::ftruncate(fd, len as libc::off_t) != 0 {
+            Err(SecretBoxError::Mmap(errno()))
+        } else {
+            let ptr = libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd,
+                0,
+            );
+            if ptr == libc::MAP_FAILED {
+                Err(SecretBoxError::Mmap(errno()))
+            } else {
+                Ok(Some(ptr as usize))
+            }
+        };
+        // The mapping keeps the memory alive on its own
+        libc::close(fd);
+        result
+    }
+
+    #[cfg(not(all(target_os = "linux", feature = "memfd-secret")))]
+    {
+        let _ = len;
+        Ok(None)
+    }
+}
+
+/// Map `len` bytes of fresh, page-aligned, read-write memory not shared with any other allocation.
+pub(crate) fn map_pages(len: usize) -> Result<usize, SecretBoxError> {
+    #[cfg(unix)]
+    {
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(SecretBoxError::Mmap(errno()));
+        }
+        // Child processes get zeroed pages instead of a copy of the secret. The pages aren't
+        // shared with other allocations, so that can't break the child.
+        #[cfg(any(target_os = "openbsd", target_os = "freebsd", target_os = "netbsd"))]
+        if unsafe { minherit(ptr, len, INHERIT_ZERO) } != 0 {
+            let err = SecretBoxError::Minherit(errno());
+            unsafe { libc::munmap(ptr, len) };
+            return Err(err);
+        }
+        Ok(ptr as usize)
+    }
+
+    #[cfg(windows)]
+    {
+        use windows_sys::Win32::System::Memory::{
+            VirtualAlloc, MEM_COMMIT, MEM_RESERVE, PAGE_READWRITE,
+        };
+
+        let ptr = unsafe {
+            VirtualAlloc(
+                std::ptr::null(),
+                len,
+                MEM_COMMIT | MEM_RESERVE,
+                PAGE_READWRITE,
+            )
+        };
+        if ptr.is_null() {
+            return Err(SecretBoxError::Mmap(errno()));
+        }
+        Ok(ptr as usize)
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = len;
+        Err(SecretBoxError::Unsupported)
+    }
+}
+
+/// Release pages mapped with [`map_pages`].
+#[cfg_attr(not(any(unix, windows)), allow(unused_variables))]
+pub(crate) unsafe fn unmap_pages(start: usize, len: usize) -> Result<(), SecretBoxError> {
+    #[cfg(unix)]
+    if libc::munmap(start as *mut c_void, len) != 0 {
+        return Err(SecretBoxError::Mmap(errno()));
+    }
+
+    #[cfg(windows)]
+    {
+        use windows_sys::Win32::System::Memory::{VirtualFree, MEM_RELEASE};
+
+        let _ = len;
+        if VirtualFree(start as *mut _, 0, MEM_RELEASE) == 0 {
+            return Err(SecretBoxError::Mmap(errno()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Ask the kernel whether every page spanning the `len` bytes at `ptr` is resident and locked.
+#[cfg(target_os = "linux")]
+pub(crate) fn is_range_locked(ptr: *const u8, len: usize) -> std::io::Result<bool> {
+    match check_range_locked(ptr, len) {
+        Ok(()) => Ok(true),
+        Err(ResidencyError::Io(err)) => Err(err),
+        Err(_) => Ok(false),
+    }
+}
+
+/// Check that every page spanning the `len` bytes at `ptr` is resident and locked, reporting
+/// the first page that isn't.
+///
+/// Residency comes from `mincore`, the lock state from the `lo` flag of the mappings in
+/// `/proc/self/smaps`.
+#[cfg(target_os = "linux")]
+pub(crate) fn check_range_locked(ptr: *const u8, len: usize) -> Result<(), ResidencyError> {
+    use std::io;
+
+    if len == 0 {
+        return Ok(());
+    }
+    let page_size = page_size().map_err(io::Error::other)?;
+    let (start, len) = aligned_region(ptr as usize, len, page_size).map_err(io::Error::other)?;
+    let end = start + len;
+
+    let mut resident = vec![0u8; len / page_size];
+    if unsafe { libc::mincore(start as *mut c_void, len, resident.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    if let Some(page) = resident.iter().position(|page| page & 1 == 0) {
+        return Err(ResidencyError::NotResident(start + page * page_size));
+    }
+
+    // Each mapping starts with its `start-end perms ...` header and ends with its flags
+    let smaps = std::fs::read_to_string("/proc/self/smaps")?;
+    let mut mapping: Option<(usize, usize)> = None;
+    for line in smaps.lines() {
+        if let Some(flags) = line.strip_prefix("VmFlags:") {
+            if let Some((map_start, map_end)) = mapping.take() {
+                if map_start < end
+                    && start < map_end
+                    && !flags.split_whitespace().any(|f| f == "lo")
+                {
+                    return Err(ResidencyError::NotLocked(map_start.max(start)));
+                }
+            }
+        } else if let Some((map_start, map_end)) = line
+            .split_whitespace()
+            .next()
+            .and_then(|range| range.split_once('-'))
+        {
+            if let (Ok(map_start), Ok(map_end)) = (
+                usize::from_str_radix(map_start, 16),
+                usize::from_str_radix(map_end, 16),
+            ) {
+                mapping = Some((map_start, map_end));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Change the access allowed to pages mapped with [`map_pages`].
+#[cfg_attr(not(any(unix, windows)), allow(unused_variables))]
+pub(crate) unsafe fn protect_memory(
+    start: usize,
+    len: usize,
+    access: Access,
+) -> Result<(), SecretBoxError> {
+    #[cfg(unix)]
+    {
+        let prot = match access {
+            Access::None => libc::PROT_NONE,
+            Access::ReadOnly => libc::PROT_READ,
+            Access::ReadWrite => libc::PROT_READ | libc::PROT_WRITE,
+        };
+        if libc::mprotect(start as *mut c_void, len, prot) != 0 {
+            return Err(SecretBoxError::Mprotect(errno()));
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        use windows_sys::Win32::System::Memory::{
+            VirtualProtect, PAGE_NOACCESS, PAGE_READONLY, PAGE_READWRITE,
+        };
+
+        let protect = match access {
+            Access::None => PAGE_NOACCESS,
+            Access::ReadOnly => PAGE_READONLY,
+            Access::ReadWrite => PAGE_READWRITE,
+        };
+        let mut old = 0;
+        if VirtualProtect(start as *const _, len, protect, &mut old) == 0 {
+            return Err(SecretBoxError::Mprotect(errno()));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn test_page_size_cached() {
+        let size = page_size().unwrap();
+        assert!(size.is_power_of_two());
+        assert_eq!(PAGE_SIZE.get(), Some(&size));
+        assert_eq!(page_size(), Ok(size));
+    }
+
+    #[test]
+    fn test_memlock_budget_accounting() {
+        let budget = MemlockBudget::new();
+        budget.cap.store(8192, Ordering::Relaxed);
+        assert_eq!(budget.reserve(4096), Ok(()));
+        assert_eq!(budget.reserve(4096), Ok(()));
+        assert_eq!(
+            budget.reserve(4096),
+            Err(SecretBoxError::MemlockBudget {
+                requested: 4096,
+                budget: 8192
+            })
+        );
+        budget.release(4096);
+        assert_eq!(budget.reserve(4096), Ok(()));
+        assert!(budget.reserve(usize::MAX).is_err());
+        assert_eq!(budget.locked.load(Ordering::Relaxed), 8192);
+    }
+
+    #[test]
+    fn test_checked_page_size() {
+        assert_eq!(checked_page_size(4096), Ok(4096));
+        assert_eq!(checked_page_size(16384), Ok(16384));
+        assert_eq!(
+            checked_page_size(0),
+            Err(SecretBoxError::InvalidPageSize(0))
+        );
+        assert_eq!(
+            checked_page_size(12288),
+            Err(SecretBoxError::InvalidPageSize(12288))
+        );
+    }
+
+    #[test]
+    fn test_aligned_region() {
+        // At a page boundary
+        assert_eq!(aligned_region(4096, 20, 4096), Ok((4096, 4096)));
+        assert_eq!(aligned_region(4096, 4096, 4096), Ok((4096, 4096)));
+        // Mid-page
+        assert_eq!(aligned_region(4096 + 10, 20, 4096), Ok((4096, 4096)));
+        assert_eq!(aligned_region(4096 - 10, 20, 4096), Ok((0, 8192)));
+        // Spanning several pages
+        assert_eq!(
+            aligned_region(4096 + 10, 3 * 4096, 4096),
+            Ok((4096, 4 * 4096))
+        );
+        assert_eq!(
+            aligned_region(16384, 2 * 16384 + 1, 16384),
+            Ok((16384, 3 * 16384))
+        );
+        // Zero length
+        assert_eq!(aligned_region(4096, 0, 4096), Ok((4096, 0)));
+        assert_eq!(aligned_region(4096 + 10, 0, 4096), Ok((4096, 0)));
+        assert_eq!(
+            aligned_region(usize::MAX - 4095, 10, 4096),
+            Err(SecretBoxError::RegionOverflow)
+        );
+        assert_eq!(
+            aligned_region(4096, usize::MAX, 4096),
+            Err(SecretBoxError::RegionOverflow)
+        );
+
+        // Fits in the address space, but no allocation is that large
+        let max = isize::MAX as usize;
+        assert_eq!(aligned_region(0, max - 4095, 4096), Ok((0, max - 4095)));
+        assert_eq!(
+            aligned_region(0, max - 4094, 4096),
+            Err(SecretBoxError::RegionOverflow)
+        );
+        assert_eq!(
+            aligned_region(4096 + 10, max - 4096, 4096),
+            Err(SecretBoxError::RegionOverflow)
+        );
+        assert_eq!(checked_region_len(max), Ok(max));
+        assert_eq!(
+            checked_region_len(max + 1),
+            Err(SecretBoxError::RegionOverflow)
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_mlock_chunked_rolls_back() {
+        let page_size = page_size().unwrap();
+        let start = map_pages(3 * page_size).unwrap();
+        // Locking fails at the unmapped page in the middle
+        unsafe { unmap_pages(start + page_size, page_size).unwrap() };
+
+        let result = unsafe { mlock_chunked(start, 3 * page_size, page_size, false) };
+        assert_eq!(result, Err(Errno(libc::ENOMEM)));
+        assert!(!is_range_locked(start as *const u8, page_size).unwrap());
+
+        unsafe {
+            unmap_pages(start, page_size).unwrap();
+            unmap_pages(start + 2 * page_size, page_size).unwrap();
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_lock_memory_overflow() {
+        let result = unsafe { lock_memory(4096 as *const u8, usize::MAX, SecretBoxBuilder::new()) };
+        assert_eq!(result.unwrap_err(), SecretBoxError::RegionOverflow);
+        let oversized = isize::MAX as usize;
+        let result = unsafe { lock_memory(4096 as *const u8, oversized, SecretBoxBuilder::new()) };
+        assert_eq!(result.unwrap_err(), SecretBoxError::RegionOverflow);
+    }
+}
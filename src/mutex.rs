@@ -0,0 +1,151 @@
+//! A secret shared between threads that may also be modified, e.g. a credential store.
+
+use core::{
+    any,
+    fmt::{self, Debug},
+};
+use std::{
+    ops::{Deref, DerefMut},
+    sync::{Mutex, MutexGuard},
+};
+
+use zeroize::Zeroize;
+
+use crate::{ExposeSecret, SecretBox, SecretGuardMut};
+
+/// A [`SecretBox`] behind a `Mutex`, for secrets that are shared and occasionally modified.
+///
+/// A `SecretBox<Mutex<S>>` only locks and zeroizes the `Mutex` itself, which doesn't know
+/// about heap data owned by `S`. Here the mutex guards the box instead, so the secret keeps
+/// whatever protection the box was created with: build it with
+/// [`SecretBox::new_with_region`] to also lock a `Vec`'s or `String`'s buffer, which is locked
+/// again if it's reallocated in a critical section.
+///
+/// ```
+/// use std::{sync::Arc, thread};
+///
+/// use shush_rs::{SecretBox, SecretMutex};
+///
+/// let token = SecretBox::new_with_region(Box::new(vec![1u8; 16]));
+/// let token = Arc::new(SecretMutex::new(token));
+/// let refresher = {
+///     let token = Arc::clone(&token);
+///     thread::spawn(move || token.lock_expose().fill(2))
+/// };
+/// refresher.join().unwrap();
+/// assert_eq!(*token.lock_expose(), [2; 16]);
+/// ```
+///
+/// A panic in a critical section doesn't poison the secret, the next [`Self::lock_expose`]
+/// sees it as the panicking thread left it.
+pub struct SecretMutex<S: Zeroize> {
+    inner: Mutex<SecretBox<S>>,
+}
+
+/// Exposes the secret of a [`SecretMutex`] mutably while holding its lock, returned by
+/// [`SecretMutex::lock_expose`].
+pub struct SecretMutexGuard<'a, S: Zeroize> {
+    // Dropped first, so the secret is relocked and concealed before the lock is released
+    guard: SecretGuardMut<'a, S>,
+    _lock: MutexGuard<'a, SecretBox<S>>,
+}
+
+impl<S: Zeroize> SecretMutex<S> {
+    /// Share `secret` between threads.
+    pub fn new(secret: SecretBox<S>) -> Self {
+        Self {
+            inner: Mutex::new(secret),
+        }
+    }
+
+    /// Lock the mutex and expose the secret mutably until the guard is dropped, blocking while
+    /// another thread holds the lock.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret can't be made accessible, see
+    /// [`ExposeSecret::expose_secret_mut`].
+    pub fn lock_expose(&self) -> SecretMutexGuard<'_, S> {
+        let mut lock = self.inner.lock().unwrap_or_else(|err| err.into_inner());
+        let secret: *mut SecretBox<S> = &mut *lock;
+        // SAFETY: the box lives in the mutex, not in the `MutexGuard`, so it stays put when
+        // the guard is moved. It's only reachable through `guard` until the lock is released,
+        // which happens after `guard` is dropped.
+        let guard = unsafe { &mut *secret }.expose_secret_mut();
+        SecretMutexGuard { guard, _lock: lock }
+    }
+
+    /// Take the secret out of the mutex.
+    pub fn into_inner(self) -> SecretBox<S> {
+        self.inner
+            .into_inner()
+            .unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl<S: Zeroize> From<SecretBox<S>> for SecretMutex<S> {
+    fn from(secret: SecretBox<S>) -> Self {
+        Self::new(secret)
+    }
+}
+
+impl<S: Zeroize> Debug for SecretMutex<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretMutex<{}>([REDACTED])", any::type_name::<S>())
+    }
+}
+
+impl<S: Zeroize> Deref for SecretMutexGuard<'_, S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        &self.guard
+    }
+}
+
+impl<S: Zeroize> DerefMut for SecretMutexGuard<'_, S> {
+    fn deref_mut(&mut self) -> &mut S {
+        &mut self.guard
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread};
+
+    use super::*;
+    use crate::memory;
+
+    #[test]
+    fn test_secret_mutex_relocks_grown_buffer() {
+        let mutex = Arc::new(SecretMutex::new(SecretBox::new_with_region(Box::new(
+            vec![1u8; 16],
+        ))));
+        let page_size = memory::page_size().unwrap();
+        let writers: Vec<_> = (0..4)
+            .map(|_| {
+                let mutex = Arc::clone(&mutex);
+                thread::spawn(move || mutex.lock_expose().extend(vec![2; page_size]))
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let secret = Arc::into_inner(mutex).unwrap().into_inner();
+        let heap_region = secret.heap_region.expect("Vec buffer is not locked");
+        let guard = secret.expose_secret();
+        assert_eq!(guard.len(), 16 + 4 * page_size);
+        let (ptr, len) = (guard.as_ptr() as usize, guard.capacity());
+        assert!(heap_region.start <= ptr && ptr + len <= heap_region.start + heap_region.len);
+    }
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn test_secret_mutex_protected() {
+        let mutex = SecretMutex::from(SecretBox::new_protected(Box::new([1u8; 32])));
+        mutex.lock_expose()[0] = 2;
+        assert_eq!(mutex.lock_expose()[..2], [2, 1]);
+        assert_eq!(format!("{:?}", mutex), "SecretMutex<[u8; 32]>([REDACTED])");
+    }
+}
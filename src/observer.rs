@@ -0,0 +1,169 @@
+//! Process-wide observer of memory locking, e.g. for metrics.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    RwLock,
+};
+
+use crate::SecretBoxError;
+
+/// Receives events about the memory locked for secrets, set with [`set_observer`].
+///
+/// Useful to export a gauge of the locked bytes, to keep an eye on the headroom left by
+/// `RLIMIT_MEMLOCK`, and a counter of failed locks. Events are delivered on the thread that
+/// creates or drops the secret, so implementations should be cheap and must not create or
+/// drop secrets themselves.
+pub trait Observer: Send + Sync {
+    /// `bytes` of memory were locked.
+    fn on_lock(&self, bytes: usize) {
+        let _ = bytes;
+    }
+
+    /// `bytes` of previously locked memory were unlocked.
+    fn on_unlock(&self, bytes: usize) {
+        let _ = bytes;
+    }
+
+    /// Locking memory failed. This is also reported when the failure was tolerated, e.g. by
+    /// [`SecretBox::new_best_effort`](crate::SecretBox::new_best_effort).
+    fn on_lock_error(&self, error: &SecretBoxError) {
+        let _ = error;
+    }
+}
+
+static OBSERVER: RwLock<Option<Box<dyn Observer>>> = RwLock::new(None);
+
+/// Fast path to skip the lock entirely while no observer is set.
+static HAS_OBSERVER: AtomicBool = AtomicBool::new(false);
+
+/// Set the observer notified about every lock and unlock, replacing the previous one.
+pub fn set_observer(observer: impl Observer + 'static) {
+    *OBSERVER.write().unwrap_or_else(|err| err.into_inner()) = Some(Box::new(observer));
+    HAS_OBSERVER.store(true, Ordering::Release);
+}
+
+/// Call `f` with the observer, if one is set.
+pub(crate) fn notify(f: impl FnOnce(&dyn Observer)) {
+    if !HAS_OBSERVER.load(Ordering::Acquire) {
+        return;
+    }
+    if let Some(observer) = &*OBSERVER.read().unwrap_or_else(|err| err.into_inner()) {
+        f(observer.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::{memory::Region, ExposeSecret, SecretBox, SecretString, SecretVec};
+
+    thread_local! {
+        static LOCKED: Cell<isize> = const { Cell::new(0) };
+        static UNLOCKED: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// Counts the bytes locked, and separately those unlocked, by the current thread, so tests
+    /// running in parallel don't interfere.
+    struct LockedBytes;
+
+    impl Observer for LockedBytes {
+        fn on_lock(&self, bytes: usize) {
+            LOCKED.with(|locked| locked.set(locked.get() + bytes as isize));
+        }
+
+        fn on_unlock(&self, bytes: usize) {
+            LOCKED.with(|locked| locked.set(locked.get() - bytes as isize));
+            UNLOCKED.with(|unlocked| unlocked.set(unlocked.get() + bytes));
+        }
+    }
+
+    #[test]
+    fn test_observer_tracks_locked_bytes() {
+        set_observer(LockedBytes);
+
+        let secret_box = SecretBox::new(Box::new([1u8; 32]));
+        let secret_vec = SecretVec::from(vec![1u8; 32]);
+        let expected = [
+            Some(secret_box.region),
+            Some(secret_vec.region),
+            secret_vec.heap_region,
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|region| region.locked)
+        .map(|region| region.len as isize)
+        .sum::<isize>();
+        assert_eq!(LOCKED.get(), expected);
+
+        drop(secret_box);
+        drop(secret_vec);
+        assert_eq!(LOCKED.get(), 0);
+    }
+
+    #[test]
+    fn test_observer_unlocks_reallocated_buffer() {
+        set_observer(LockedBytes);
+
+        let mut secret_box = SecretBox::new_with_region(Box::new(String::from("short")));
+        let locked = |secret_box: &SecretBox<String>| {
+            [Some(secret_box.region), secret_box.heap_region]
+                .into_iter()
+                .flatten()
+                .filter(|region| region.locked)
+                .map(|region| region.len as isize)
+                .sum::<isize>()
+        };
+        assert_eq!(LOCKED.get(), locked(&secret_box));
+
+        // Growing moves the buffer, the old one's pages must be unlocked
+        secret_box
+            .expose_secret_mut()
+            .push_str(&"longer".repeat(4096));
+        assert_eq!(LOCKED.get(), locked(&secret_box));
+
+        drop(secret_box);
+        assert_eq!(LOCKED.get(), 0);
+    }
+
+    #[test]
+    fn test_observer_keeps_converted_buffer_locked() {
+        set_observer(LockedBytes);
+
+        let string = SecretString::new("hunter2".repeat(64));
+        let heap_region = string.as_inner().heap_region.expect("buffer is not locked");
+        let span = |region: Option<Region>| region.map(|region| (region.start, region.len));
+
+        // Only the old box's own memory is unlocked, the buffer's lock moves along with it
+        let unlocked = UNLOCKED.get();
+        let own_region = string.as_inner().region;
+        let bytes = string.into_bytes();
+        let expected = if own_region.locked { own_region.len } else { 0 };
+        assert_eq!(UNLOCKED.get() - unlocked, expected);
+        assert_eq!(span(bytes.heap_region), span(Some(heap_region)));
+
+        let unlocked = UNLOCKED.get();
+        let own_region = bytes.region;
+        let string = bytes.into_string().unwrap();
+        let expected = if own_region.locked { own_region.len } else { 0 };
+        assert_eq!(UNLOCKED.get() - unlocked, expected);
+        assert_eq!(span(string.as_inner().heap_region), span(Some(heap_region)));
+        assert_eq!(string.expose_secret().len(), 7 * 64);
+    }
+
+    #[test]
+    fn test_observer_unlocks_failed_ctr() {
+        set_observer(LockedBytes);
+
+        let result = SecretBox::<[u8; 32]>::try_new_with_ctr(|| Err("ctr failed"));
+        assert!(result.is_err());
+        assert_eq!(LOCKED.get(), 0);
+
+        let result = std::panic::catch_unwind(|| {
+            SecretBox::<[u8; 32]>::new_with_ctr(|| panic!("ctr failed"))
+        });
+        assert!(result.is_err());
+        assert_eq!(LOCKED.get(), 0);
+    }
+}
@@ -0,0 +1,85 @@
+//! Overwriting secrets with patterns before they're zeroized.
+
+use std::{
+    hash::{BuildHasher, RandomState},
+    ptr::{self, NonNull},
+    sync::atomic::{compiler_fence, Ordering},
+};
+
+/// What to overwrite a secret's bytes with before they're zeroized, see
+/// [`SecretBox::zeroize_with`](crate::SecretBox::zeroize_with).
+///
+/// Every pattern ends with the regular zeroization, so the secret is all zeros afterwards no
+/// matter which one is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum ZeroizePattern {
+    /// Only zeroize the secret, like dropping it does by default.
+    #[default]
+    Zeros,
+    /// Overwrite every byte with `0xFF` first.
+    Ones,
+    /// Overwrite the bytes with random data first. The data comes from std's randomly keyed
+    /// SipHash, it's meant to hide the previous contents, not as key material.
+    Random,
+    /// Overwrite the bytes with `0xFF`, then `0x00`, then random data.
+    MultiPass,
+}
+
+/// Overwrite `bytes` as `pattern` asks, without the final zeroization.
+pub(crate) fn overwrite(bytes: &mut [u8], pattern: ZeroizePattern) {
+    match pattern {
+        ZeroizePattern::Zeros => {}
+        ZeroizePattern::Ones => fill(bytes, |_| 0xFF),
+        ZeroizePattern::Random => fill_random(bytes),
+        ZeroizePattern::MultiPass => {
+            fill(bytes, |_| 0xFF);
+            fill(bytes, |_| 0x00);
+            fill_random(bytes);
+        }
+    }
+}
+
+/// Type-erased [`overwrite`] of a secret's bytes.
+pub(crate) type OverwriteFn = unsafe fn(NonNull<()>, ZeroizePattern);
+
+pub(crate) unsafe fn overwrite_erased<S: AsMut<[u8]>>(
+    secret: NonNull<()>,
+    pattern: ZeroizePattern,
+) {
+    overwrite(secret.cast::<S>().as_mut().as_mut(), pattern)
+}
+
+fn fill_random(bytes: &mut [u8]) {
+    let state = RandomState::new();
+    fill(bytes, |index| {
+        state.hash_one(index / 8).to_le_bytes()[index % 8]
+    });
+}
+
+/// Write `byte(index)` to every byte with volatile writes, so the pass isn't optimized away
+/// as a dead store.
+fn fill(bytes: &mut [u8], byte: impl Fn(usize) -> u8) {
+    for (index, slot) in bytes.iter_mut().enumerate() {
+        unsafe { ptr::write_volatile(slot, byte(index)) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overwrite() {
+        let mut bytes = [1u8; 64];
+        overwrite(&mut bytes, ZeroizePattern::Zeros);
+        assert_eq!(bytes, [1; 64]);
+        overwrite(&mut bytes, ZeroizePattern::Ones);
+        assert_eq!(bytes, [0xFF; 64]);
+        overwrite(&mut bytes, ZeroizePattern::Random);
+        assert!(bytes.iter().any(|&byte| byte != 0xFF));
+        overwrite(&mut bytes, ZeroizePattern::MultiPass);
+        assert!(bytes.iter().any(|&byte| byte != 0));
+    }
+}
@@ -0,0 +1,95 @@
+//! Random secrets, e.g. freshly generated keys and nonces, from the operating system's
+//! CSPRNG via [`getrandom`].
+//!
+//! The secret's memory is locked first and the random bytes are written straight into it,
+//! they never pass through an unlocked buffer.
+
+use crate::{error::fail, ExposeSecret, SecretArray, SecretBoxError, SecretVec};
+
+/// Fill `bytes` from the operating system's CSPRNG.
+fn fill(bytes: &mut [u8]) -> Result<(), SecretBoxError> {
+    getrandom::fill(bytes).map_err(SecretBoxError::Random)
+}
+
+impl SecretVec<u8> {
+    /// Create a secret vector of `len` random bytes, e.g. a new key.
+    ///
+    /// ```
+    /// use shush_rs::{ExposeSecret, SecretVec};
+    ///
+    /// let key = SecretVec::random(32);
+    /// assert_eq!(key.expose_secret().len(), 32);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret's memory can't be locked or the operating system can't provide
+    /// random bytes. See [`Self::try_random`] for a non-panicking alternative.
+    pub fn random(len: usize) -> Self {
+        Self::try_random(len).unwrap_or_else(|err| fail(err))
+    }
+
+    /// Same as [`Self::random`], but returns an error if the secret's memory can't be locked
+    /// or the operating system can't provide random bytes.
+    pub fn try_random(len: usize) -> Result<Self, SecretBoxError> {
+        let mut secret = Self::try_new_with_len(len)?;
+        fill(&mut secret.expose_secret_mut())?;
+        Ok(secret)
+    }
+}
+
+impl<const N: usize> SecretArray<u8, N> {
+    /// Create a secret array of random bytes, e.g. a new symmetric key.
+    ///
+    /// ```
+    /// use shush_rs::SecretArray;
+    ///
+    /// let key = SecretArray::<u8, 32>::random();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret's memory can't be locked or the operating system can't provide
+    /// random bytes. See [`Self::try_random`] for a non-panicking alternative.
+    pub fn random() -> Self {
+        Self::try_random().unwrap_or_else(|err| fail(err))
+    }
+
+    /// Same as [`Self::random`], but returns an error if the secret's memory can't be locked
+    /// or the operating system can't provide random bytes.
+    pub fn try_random() -> Result<Self, SecretBoxError> {
+        let mut secret = Self::try_new(Box::new([0; N]))?;
+        fill(&mut *secret.expose_secret_mut())?;
+        Ok(secret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_vec_random() {
+        let secret = SecretVec::random(64);
+        assert_eq!(secret.expose_secret().len(), 64);
+        // 64 zero bytes from a CSPRNG are practically impossible
+        assert!(secret.expose_secret().iter().any(|&byte| byte != 0));
+        assert!(secret.heap_region.is_some());
+        assert_ne!(
+            *secret.expose_secret(),
+            *SecretVec::random(64).expose_secret()
+        );
+
+        assert!(SecretVec::random(0).expose_secret().is_empty());
+    }
+
+    #[test]
+    fn test_secret_array_random() {
+        let secret = SecretArray::<u8, 32>::random();
+        assert!(secret.expose_secret().iter().any(|&byte| byte != 0));
+        assert_ne!(
+            *secret.expose_secret(),
+            *SecretArray::<u8, 32>::random().expose_secret()
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! Secrets that are built once and then only read, shared between their readers.
+
+use core::fmt::{self, Debug};
+use std::sync::Arc;
+
+use zeroize::Zeroize;
+
+use crate::SecretBox;
+
+/// A secret that can no longer be mutated, created by [`SecretBox::freeze`].
+///
+/// Cloning a read-only secret hands out another reference to the same locked secret, like
+/// cloning an `Arc`, it never copies the secret into new memory. The secret is zeroized and
+/// unlocked when the last clone is dropped.
+///
+/// ```
+/// use std::thread;
+///
+/// use shush_rs::{ExposeSecret, SecretBox};
+///
+/// let mut credential = SecretBox::new(Box::new([0u8; 32]));
+/// credential.expose_secret_mut().fill(7);
+/// let credential = credential.freeze();
+///
+/// let reader = credential.clone();
+/// let first = thread::spawn(move || reader.with_exposed(|key| key[0]));
+/// assert_eq!(first.join().unwrap(), 7);
+/// ```
+///
+/// Read-only secrets offer no mutable access:
+///
+/// ```compile_fail
+/// use shush_rs::{ExposeSecret, SecretBox};
+///
+/// let credential = SecretBox::new(Box::new([0u8; 32])).freeze();
+/// credential.expose_secret_mut();
+/// ```
+pub struct ReadOnlySecret<S: Zeroize + ?Sized> {
+    inner: Arc<SecretBox<S>>,
+}
+
+impl<S: Zeroize + ?Sized> SecretBox<S> {
+    /// Freeze the secret into a [`ReadOnlySecret`], e.g. once a credential is set up and
+    /// should only be read from then on.
+    ///
+    /// The secret stays where it is, locked and protected as before.
+    pub fn freeze(self) -> ReadOnlySecret<S> {
+        ReadOnlySecret {
+            inner: Arc::new(self),
+        }
+    }
+}
+
+impl<S: Zeroize + ?Sized> ReadOnlySecret<S> {
+    /// Expose the secret only for the duration of `f`, see [`SecretBox::with_exposed`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret can't be made accessible, see
+    /// [`ExposeSecret::expose_secret`](crate::ExposeSecret::expose_secret).
+    pub fn with_exposed<R>(&self, f: impl FnOnce(&S) -> R) -> R {
+        self.inner.with_exposed(f)
+    }
+
+    /// Whether `self` and `other` share the same secret.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl<S: Zeroize + ?Sized> Clone for ReadOnlySecret<S> {
+    /// Share the same secret, without copying it.
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<S: Zeroize + ?Sized> Debug for ReadOnlySecret<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ReadOnlySecret").field(&self.inner).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_only_secret_shares() {
+        let secret = SecretBox::new(Box::new(String::from("hunter2"))).freeze();
+        let shared = secret.clone();
+        assert!(secret.ptr_eq(&shared));
+        let ptr = secret.with_exposed(|string| string as *const String);
+        assert_eq!(shared.with_exposed(|string| string as *const String), ptr);
+        assert_eq!(shared.with_exposed(|string| string.len()), 7);
+        assert_eq!(
+            format!("{:?}", shared),
+            "ReadOnlySecret(SecretBox<alloc::string::String>([REDACTED]))"
+        );
+
+        drop(secret);
+        assert_eq!(shared.with_exposed(|string| string.clone()), "hunter2");
+    }
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn test_read_only_secret_protected() {
+        let secret = SecretBox::new_protected(Box::new([1u8; 32])).freeze();
+        assert_eq!(secret.clone().with_exposed(|key| key[0]), 1);
+    }
+}
@@ -0,0 +1,270 @@
+//! Short-lived secret buffers carved out of a locked per-thread arena.
+
+use core::fmt::{self, Debug};
+use std::{
+    cell::{Cell, OnceCell},
+    ops::{Deref, DerefMut},
+    rc::Rc,
+};
+
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::{
+    error::fail,
+    memory::{self, lock_memory, unlock_memory, Region},
+    report_drop_error, SecretBoxBuilder, SecretBoxError,
+};
+
+/// Bytes mapped and locked for each thread's arena, rounded up to whole pages.
+const ARENA_LEN: usize = 64 * 1024;
+
+/// Alignment of every buffer handed out by the arena, enough for SIMD loads.
+const ALIGN: usize = 16;
+
+thread_local! {
+    /// Created on the first allocation, `None` if the arena can't be mapped and locked.
+    static ARENA: OnceCell<Option<Rc<Arena>>> = const { OnceCell::new() };
+}
+
+/// Locked pages of one thread, handed out as a stack.
+struct Arena {
+    start: usize,
+    len: usize,
+    region: Region,
+    /// Offset of the first free byte.
+    top: Cell<usize>,
+    /// Number of buffers handed out and not dropped yet.
+    live: Cell<usize>,
+}
+
+impl Arena {
+    fn new() -> Result<Self, SecretBoxError> {
+        let page_size = memory::page_size()?;
+        let len = ARENA_LEN.div_ceil(page_size) * page_size;
+        let start = memory::map_pages(len)?;
+        match unsafe { lock_memory(start as *const u8, len, SecretBoxBuilder::new()) } {
+            Ok(region) => Ok(Self {
+                start,
+                len,
+                region,
+                top: Cell::new(0),
+                live: Cell::new(0),
+            }),
+            Err(err) => {
+                if let Err(err) = unsafe { memory::unmap_pages(start, len) } {
+                    report_drop_error(&err);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Hand out `len` bytes, returning their offset, or `None` if the arena is full.
+    fn alloc(&self, len: usize) -> Option<usize> {
+        let offset = self.top.get().next_multiple_of(ALIGN);
+        let end = offset.checked_add(len).filter(|&end| end <= self.len)?;
+        self.top.set(end);
+        self.live.set(self.live.get() + 1);
+        Some(offset)
+    }
+
+    /// Take back the `len` bytes at `offset`, which were zeroized already.
+    fn free(&self, offset: usize, len: usize) {
+        let live = self.live.get() - 1;
+        self.live.set(live);
+        if live == 0 {
+            self.top.set(0);
+        } else if offset + len == self.top.get() {
+            // Buffers are usually dropped in reverse order, which frees them right away
+            self.top.set(offset);
+        }
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        unsafe {
+            std::slice::from_raw_parts_mut(self.start as *mut u8, self.len).zeroize();
+            for err in unlock_memory(self.region) {
+                report_drop_error(&err);
+            }
+            if let Err(err) = memory::unmap_pages(self.start, self.len) {
+                report_drop_error(&err);
+            }
+        }
+    }
+}
+
+/// Where the bytes of a [`ScratchSecret`] live.
+enum Storage {
+    Arena {
+        arena: Rc<Arena>,
+        offset: usize,
+        len: usize,
+    },
+    /// Locked on its own when the arena is full or unavailable.
+    Heap { data: Box<[u8]>, region: Region },
+}
+
+/// A short-lived secret buffer, e.g. for intermediate values in a crypto inner loop.
+///
+/// Locking and unlocking memory takes a syscall each, which adds up for many small buffers.
+/// Scratch secrets are instead carved out of a per-thread arena of pages that are locked
+/// once, when the thread allocates its first scratch secret, and stay locked until the thread
+/// exits. Dropping a scratch secret zeroizes its bytes and returns them to the arena.
+///
+/// The arena holds 64 KiB. Buffers that don't fit, or all buffers if the arena can't be
+/// locked, are locked on their own like a [`SecretBox`](crate::SecretBox). The arena hands
+/// out memory as a stack, buffers dropped in reverse order of their allocation are reused
+/// right away, others once every scratch secret of the thread is dropped.
+///
+/// ```
+/// use shush_rs::ScratchSecret;
+///
+/// for block in 0..1000u32 {
+///     let mut tmp = ScratchSecret::alloc(32);
+///     tmp[..4].copy_from_slice(&block.to_le_bytes());
+///     // ... derive something from `tmp` ...
+/// }
+/// ```
+///
+/// A scratch secret belongs to the thread that allocated it, it can't be sent to another one.
+pub struct ScratchSecret {
+    storage: Storage,
+}
+
+impl ScratchSecret {
+    /// Allocate `len` zeroed bytes of locked memory.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory can't be locked. See [`Self::try_alloc`] for a non-panicking
+    /// alternative.
+    pub fn alloc(len: usize) -> Self {
+        Self::try_alloc(len).unwrap_or_else(|err| fail(err))
+    }
+
+    /// Same as [`Self::alloc`], but returns an error if the memory can't be locked.
+    pub fn try_alloc(len: usize) -> Result<Self, SecretBoxError> {
+        let arena =
+            ARENA.with(|arena| arena.get_or_init(|| Arena::new().ok().map(Rc::new)).clone());
+        if let Some(arena) = arena {
+            if let Some(offset) = arena.alloc(len) {
+                return Ok(Self {
+                    storage: Storage::Arena { arena, offset, len },
+                });
+            }
+        }
+
+        let data = vec![0; len].into_boxed_slice();
+        let region = unsafe { lock_memory(data.as_ptr(), len, SecretBoxBuilder::new())? };
+        Ok(Self {
+            storage: Storage::Heap { data, region },
+        })
+    }
+}
+
+impl Deref for ScratchSecret {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &self.storage {
+            Storage::Arena { arena, offset, len } => unsafe {
+                std::slice::from_raw_parts((arena.start + offset) as *const u8, *len)
+            },
+            Storage::Heap { data, .. } => data,
+        }
+    }
+}
+
+impl DerefMut for ScratchSecret {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        match &mut self.storage {
+            Storage::Arena { arena, offset, len } => unsafe {
+                std::slice::from_raw_parts_mut((arena.start + *offset) as *mut u8, *len)
+            },
+            Storage::Heap { data, .. } => data,
+        }
+    }
+}
+
+impl Zeroize for ScratchSecret {
+    fn zeroize(&mut self) {
+        (**self).zeroize()
+    }
+}
+
+impl Drop for ScratchSecret {
+    fn drop(&mut self) {
+        self.zeroize();
+        match &self.storage {
+            Storage::Arena { arena, offset, len } => arena.free(*offset, *len),
+            Storage::Heap { region, .. } => {
+                for err in unsafe { unlock_memory(*region) } {
+                    report_drop_error(&err);
+                }
+            }
+        }
+    }
+}
+
+impl ZeroizeOnDrop for ScratchSecret {}
+
+impl Debug for ScratchSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ScratchSecret({} bytes: [REDACTED])", self.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn in_arena(scratch: &ScratchSecret) -> bool {
+        matches!(scratch.storage, Storage::Arena { .. })
+    }
+
+    #[test]
+    fn test_scratch_secret_reuses_arena() {
+        let mut first = ScratchSecret::alloc(32);
+        assert_eq!(*first, [0; 32]);
+        first.fill(1);
+        let first_ptr = first.as_ptr();
+        assert!(in_arena(&first) || cfg!(not(any(unix, windows))));
+        assert_eq!(first_ptr as usize % ALIGN, 0);
+        drop(first);
+
+        // Freed in order, the next buffer takes the same, zeroized bytes
+        let second = ScratchSecret::alloc(32);
+        assert_eq!(second.as_ptr(), first_ptr);
+        assert_eq!(*second, [0; 32]);
+    }
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn test_scratch_secret_out_of_order() {
+        let first = ScratchSecret::alloc(16);
+        let second = ScratchSecret::alloc(16);
+        let (first_ptr, second_ptr) = (first.as_ptr(), second.as_ptr());
+        assert_ne!(first_ptr, second_ptr);
+
+        drop(first);
+        let third = ScratchSecret::alloc(16);
+        assert_ne!(third.as_ptr(), first_ptr);
+        drop(second);
+        drop(third);
+        assert_eq!(ScratchSecret::alloc(16).as_ptr(), first_ptr);
+    }
+
+    #[test]
+    fn test_scratch_secret_overflows_to_heap() {
+        let large = ScratchSecret::alloc(ARENA_LEN * 2);
+        assert!(!in_arena(&large));
+        assert_eq!(large.len(), ARENA_LEN * 2);
+        assert!(large.iter().all(|&byte| byte == 0));
+        assert_eq!(
+            format!("{:?}", large),
+            "ScratchSecret(131072 bytes: [REDACTED])"
+        );
+    }
+}
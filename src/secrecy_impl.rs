@@ -0,0 +1,132 @@
+//! Interoperability with the [`secrecy`] crate, for APIs that take its `SecretBox`,
+//! `SecretString` or `ExposeSecret`.
+//!
+//! `secrecy` only zeroizes its secrets, it doesn't lock them into memory or exclude them from
+//! core dumps. A secret converted into a `secrecy` type loses this crate's protection from then
+//! on, while implementing [`secrecy::ExposeSecret`] keeps the secret where it is and lends it
+//! out protected as usual.
+//!
+//! `secrecy::ExposeSecret` hands out a plain reference rather than a guard, so it can't make
+//! the pages of a [`SecretBox::new_protected`] secret accessible for as long as the reference
+//! lives. Such secrets panic when exposed through it.
+
+use core::mem;
+
+use zeroize::Zeroize;
+
+use crate::{error::fail, ExposeSecret, SecretBox, SecretString};
+
+impl<S: Zeroize + ?Sized> SecretBox<S> {
+    /// Expose the secret without a guard, for traits that return plain references.
+    fn expose_unguarded(&self) -> &S {
+        assert!(
+            self.idle_protection.is_none(),
+            "secrets protected while idle can't be exposed through secrecy::ExposeSecret"
+        );
+        self.reassert_dump_exclusion()
+            .unwrap_or_else(|err| fail(err));
+        unsafe { self.inner_secret.as_ref() }
+    }
+}
+
+impl<S: Zeroize + ?Sized> secrecy::ExposeSecret<S> for SecretBox<S> {
+    /// Expose the secret to APIs taking a `secrecy::ExposeSecret`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret is protected while idle, see [`SecretBox::new_protected`], or if its
+    /// memory can't be excluded from core dumps again.
+    fn expose_secret(&self) -> &S {
+        self.expose_unguarded()
+    }
+}
+
+impl secrecy::ExposeSecret<str> for SecretString {
+    /// Expose the string to APIs taking a `secrecy::ExposeSecret`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the string's memory can't be excluded from core dumps again.
+    fn expose_secret(&self) -> &str {
+        self.as_inner().expose_unguarded()
+    }
+}
+
+impl<S: Zeroize + Default> From<secrecy::SecretBox<S>> for SecretBox<S> {
+    /// Move the secret into locked memory. Heap data owned by the value, like a `Vec`'s, is
+    /// moved along but not locked, see [`SecretBox::new_with_region`] for that.
+    fn from(mut secret: secrecy::SecretBox<S>) -> Self {
+        SecretBox::new_with_ctr(|| {
+            mem::take(secrecy::ExposeSecretMut::expose_secret_mut(&mut secret))
+        })
+    }
+}
+
+impl<S: Zeroize> From<SecretBox<S>> for secrecy::SecretBox<S> {
+    /// Hand the secret to `secrecy`, unlocking its memory, see [`SecretBox::into_inner`].
+    fn from(secret: SecretBox<S>) -> Self {
+        secrecy::SecretBox::new(secret.into_inner())
+    }
+}
+
+impl From<secrecy::SecretString> for SecretString {
+    /// Copy the string into a locked buffer. The `secrecy` string is zeroized when it's
+    /// dropped.
+    fn from(secret: secrecy::SecretString) -> Self {
+        let string = secrecy::ExposeSecret::expose_secret(&secret);
+        let mut buffer = String::with_capacity(string.len());
+        buffer.push_str(string);
+        SecretString::new(buffer)
+    }
+}
+
+impl From<SecretString> for secrecy::SecretString {
+    /// Copy the string to `secrecy`, outside of locked memory. This string is zeroized when
+    /// it's dropped.
+    fn from(secret: SecretString) -> Self {
+        secrecy::SecretString::from(&*secret.expose_secret())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::ExposeSecret as _;
+
+    use super::*;
+
+    fn takes_secrecy(secret: &impl secrecy::ExposeSecret<str>) -> usize {
+        secret.expose_secret().len()
+    }
+
+    #[test]
+    fn test_secrecy_expose_secret() {
+        let secret = SecretString::new(String::from("hunter2"));
+        assert_eq!(takes_secrecy(&secret), 7);
+
+        let secret_box = SecretBox::new(Box::new([1u8; 32]));
+        assert_eq!(secrecy::ExposeSecret::expose_secret(&secret_box), &[1; 32]);
+    }
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    #[should_panic(expected = "protected while idle")]
+    fn test_secrecy_expose_protected() {
+        let secret_box = SecretBox::new_protected(Box::new([1u8; 32]));
+        secrecy::ExposeSecret::expose_secret(&secret_box);
+    }
+
+    #[test]
+    fn test_secrecy_conversions() {
+        let secrecy_box = secrecy::SecretBox::new(Box::new(vec![1u8, 2, 3]));
+        // `SecretVec::from` takes a plain `Vec`
+        let secret_box: SecretBox<Vec<u8>> = secrecy_box.into();
+        assert_eq!(*ExposeSecret::expose_secret(&secret_box), [1, 2, 3]);
+        let secrecy_box = secrecy::SecretBox::from(secret_box);
+        assert_eq!(*secrecy_box.expose_secret(), [1, 2, 3]);
+
+        let secret = SecretString::from(secrecy::SecretString::from("hunter2"));
+        assert_eq!(&*ExposeSecret::expose_secret(&secret), "hunter2");
+        let secrecy_string = secrecy::SecretString::from(secret);
+        assert_eq!(secrecy_string.expose_secret(), "hunter2");
+    }
+}
@@ -0,0 +1,126 @@
+//! `serde` support.
+//!
+//! Secrets are deserialized straight into locked memory. Serializing a secret fails, unless the
+//! `serde-redacted` feature is enabled, in which case `"[REDACTED]"` is written instead of the
+//! secret.
+//!
+//! **Note:** temporary buffers used by the deserializer itself (e.g. for unescaping a JSON
+//! string) are outside this crate's control and are not zeroized.
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use zeroize::Zeroize;
+
+use crate::{SecretBox, SecretString};
+
+impl<'de, S: Zeroize + Deserialize<'de>> Deserialize<'de> for SecretBox<S> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let boxed_secret = Box::<S>::deserialize(deserializer)?;
+        SecretBox::try_new(boxed_secret).map_err(de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let secret = String::deserialize(deserializer)?;
+        SecretString::try_new(secret).map_err(de::Error::custom)
+    }
+}
+
+impl<S: Zeroize + ?Sized> Serialize for SecretBox<S> {
+    fn serialize<R: Serializer>(&self, serializer: R) -> Result<R::Ok, R::Error> {
+        serialize_redacted(serializer)
+    }
+}
+
+impl Serialize for SecretString {
+    fn serialize<R: Serializer>(&self, serializer: R) -> Result<R::Ok, R::Error> {
+        serialize_redacted(serializer)
+    }
+}
+
+#[cfg(feature = "serde-json")]
+impl SecretString {
+    /// Move the string at `pointer` out of a parsed JSON `value` into a secret, e.g. to pull
+    /// a password out of a config blob.
+    ///
+    /// The string's buffer is moved into the secret as is, so the plaintext doesn't outlive
+    /// the secret in the unprotected `Value` tree. The string is replaced by `null`. Returns
+    /// `None` if there's no string at `pointer`, see [`serde_json::Value::pointer`] for its
+    /// syntax.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret's memory can't be locked.
+    pub fn take_from_json(value: &mut serde_json::Value, pointer: &str) -> Option<Self> {
+        let slot = value.pointer_mut(pointer)?;
+        let serde_json::Value::String(secret) = slot else {
+            return None;
+        };
+        let secret = std::mem::take(secret);
+        *slot = serde_json::Value::Null;
+        Some(Self::new(secret))
+    }
+}
+
+#[cfg(not(feature = "serde-redacted"))]
+fn serialize_redacted<R: Serializer>(_serializer: R) -> Result<R::Ok, R::Error> {
+    Err(serde::ser::Error::custom("refusing to serialize a secret"))
+}
+
+#[cfg(feature = "serde-redacted")]
+fn serialize_redacted<R: Serializer>(serializer: R) -> Result<R::Ok, R::Error> {
+    serializer.serialize_str("[REDACTED]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ExposeSecret;
+
+    #[derive(Deserialize, Serialize)]
+    struct Config {
+        token: SecretString,
+        key: SecretBox<Vec<u8>>,
+    }
+
+    const CONFIG: &str = r#"{"token":"hunter2","key":[1,2,3]}"#;
+
+    #[test]
+    fn test_deserialize_secrets() {
+        let config: Config = serde_json::from_str(CONFIG).unwrap();
+        assert_eq!(&*config.token.expose_secret(), "hunter2");
+        assert_eq!(*config.key.expose_secret(), vec![1, 2, 3]);
+    }
+
+    #[cfg(feature = "serde-json")]
+    #[test]
+    fn test_take_from_json() {
+        let mut value: serde_json::Value = serde_json::from_str(CONFIG).unwrap();
+        let buffer = value["token"].as_str().unwrap().as_ptr();
+
+        let token = SecretString::take_from_json(&mut value, "/token").unwrap();
+        assert_eq!(&*token.expose_secret(), "hunter2");
+        assert_eq!(token.expose_secret().as_ptr(), buffer);
+        assert!(value["token"].is_null());
+
+        assert!(SecretString::take_from_json(&mut value, "/key").is_none());
+        assert!(SecretString::take_from_json(&mut value, "/missing").is_none());
+    }
+
+    #[cfg(not(feature = "serde-redacted"))]
+    #[test]
+    fn test_serialize_secrets_fails() {
+        let config: Config = serde_json::from_str(CONFIG).unwrap();
+        assert!(serde_json::to_string(&config).is_err());
+    }
+
+    #[cfg(feature = "serde-redacted")]
+    #[test]
+    fn test_serialize_secrets_redacted() {
+        let config: Config = serde_json::from_str(CONFIG).unwrap();
+        assert_eq!(
+            serde_json::to_string(&config).unwrap(),
+            r#"{"token":"[REDACTED]","key":"[REDACTED]"}"#
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! Small secrets of variable length stored inline in a [`SmallVec`].
+
+use std::{
+    mem::{size_of, MaybeUninit},
+    ops::{Deref, DerefMut},
+    ptr,
+};
+
+use smallvec::{Array, SmallVec};
+use zeroize::Zeroize;
+
+use crate::{CloneableSecret, DeepZeroize, LockableRegion, SecretBox};
+
+/// A [`SmallVec`] that can be kept in a [`SecretBox`], see [`SecretSmallVec`].
+///
+/// Zeroizing it scrubs the elements, the spare capacity and the inline storage left behind
+/// when the vector spilled to the heap, and frees a heap buffer.
+#[derive(Default)]
+pub struct ZeroizeSmallVec<A: Array>(pub SmallVec<A>);
+
+/// Convenient type alias for short secrets of variable length, e.g. keys of a few sizes
+///
+/// Up to the array's length the elements are stored inline in the locked allocation of the
+/// box, no heap buffer is involved. If the vector spills to the heap through
+/// [`ExposeSecret::expose_secret_mut`](crate::ExposeSecret::expose_secret_mut), the heap
+/// buffer is locked when the guard is dropped, like with [`SecretBox::new_with_region`].
+///
+/// ```
+/// use shush_rs::{smallvec::SmallVec, ExposeSecret, SecretSmallVec};
+///
+/// let mut key = SecretSmallVec::<[u8; 32]>::from_smallvec(SmallVec::from_slice(&[1; 16]));
+/// key.expose_secret_mut().extend_from_slice(&[2; 16]);
+/// assert_eq!(key.expose_secret().len(), 32);
+/// ```
+pub type SecretSmallVec<A> = SecretBox<ZeroizeSmallVec<A>>;
+
+impl<A: Array> SecretSmallVec<A>
+where
+    A::Item: Zeroize,
+{
+    /// Create a secret small vector, locking the heap buffer if it has spilled already.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret's memory can't be locked.
+    pub fn from_smallvec(small_vec: SmallVec<A>) -> Self {
+        SecretBox::new_with_region(Box::new(ZeroizeSmallVec(small_vec)))
+    }
+}
+
+impl<A: Array> Deref for ZeroizeSmallVec<A> {
+    type Target = SmallVec<A>;
+
+    fn deref(&self) -> &SmallVec<A> {
+        &self.0
+    }
+}
+
+impl<A: Array> DerefMut for ZeroizeSmallVec<A> {
+    fn deref_mut(&mut self) -> &mut SmallVec<A> {
+        &mut self.0
+    }
+}
+
+impl<A: Array> Zeroize for ZeroizeSmallVec<A>
+where
+    A::Item: Zeroize,
+{
+    fn zeroize(&mut self) {
+        self.0.iter_mut().for_each(Zeroize::zeroize);
+        self.0.clear();
+        let capacity = self.0.capacity() * size_of::<A::Item>();
+        unsafe {
+            std::slice::from_raw_parts_mut(self.0.as_mut_ptr().cast::<MaybeUninit<u8>>(), capacity)
+        }
+        .zeroize();
+        // Moves back inline and frees the heap buffer
+        self.0.shrink_to_fit();
+
+        // The inline storage may still hold elements from before the vector spilled. An
+        // empty inline `SmallVec` owns nothing, so it can be overwritten without dropping.
+        let this = ptr::addr_of_mut!(self.0);
+        unsafe {
+            std::slice::from_raw_parts_mut(this.cast::<u8>(), size_of::<SmallVec<A>>()).zeroize();
+            ptr::write(this, SmallVec::new());
+        }
+    }
+}
+
+impl<A: Array> Clone for ZeroizeSmallVec<A>
+where
+    A::Item: Clone,
+{
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<A: Array> DeepZeroize for ZeroizeSmallVec<A> where A::Item: Zeroize {}
+
+impl<A: Array> CloneableSecret for ZeroizeSmallVec<A> where A::Item: CloneableSecret {}
+
+impl<A: Array> LockableRegion for ZeroizeSmallVec<A> {
+    fn region(&self) -> (*const u8, usize) {
+        (
+            self.0.as_ptr().cast(),
+            self.0.capacity() * size_of::<A::Item>(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ExposeSecret;
+
+    #[test]
+    fn test_secret_small_vec_inline() {
+        let secret = SecretSmallVec::<[u8; 32]>::from_smallvec(SmallVec::from_slice(&[1; 16]));
+        // Inline elements are locked with the box itself
+        assert!(secret.heap_region.is_none());
+        assert_eq!(*secret.expose_secret().as_slice(), [1; 16]);
+    }
+
+    #[test]
+    fn test_secret_small_vec_spill() {
+        let mut secret = SecretSmallVec::<[u8; 8]>::from_smallvec(SmallVec::from_slice(&[1; 8]));
+        secret.expose_secret_mut().push(2);
+        assert!(secret.expose_secret().spilled());
+
+        let heap_region = secret.heap_region.expect("spilled buffer is not locked");
+        let (ptr, len) = secret.expose_secret().region();
+        assert!(heap_region.start <= ptr as usize);
+        assert!(ptr as usize + len <= heap_region.start + heap_region.len);
+
+        // Moving back inline releases the heap buffer's lock
+        secret.expose_secret_mut().truncate(4);
+        secret.expose_secret_mut().shrink_to_fit();
+        assert!(!secret.expose_secret().spilled());
+        assert!(secret.heap_region.is_none());
+        assert_eq!(*secret.expose_secret().as_slice(), [1; 4]);
+    }
+
+    #[test]
+    fn test_zeroize_small_vec() {
+        let mut small_vec = ZeroizeSmallVec::<[u8; 8]>(SmallVec::from_slice(&[1; 8]));
+        small_vec.push(2);
+        small_vec.zeroize();
+        assert!(small_vec.is_empty());
+        assert!(!small_vec.spilled());
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
+                ptr::addr_of!(small_vec.0).cast::<u8>(),
+                size_of::<SmallVec<[u8; 8]>>(),
+            )
+        };
+        assert!(!bytes.contains(&1));
+    }
+}
@@ -0,0 +1,253 @@
+use core::fmt::{self, Debug};
+use std::{env::VarError, str::FromStr};
+
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::{
+    error::fail, ExposeError, ExposeSecret, SecretBox, SecretBoxError, SecretGuard, SecretGuardMut,
+    SecretVec,
+};
+
+/// Secret wrapped string.
+///
+/// Unlike a `SecretBox<String>`, which only locks the `String` itself (its pointer, length and
+/// capacity), this also locks the heap buffer holding the actual characters. The secret is
+/// exposed as a `str` so it can't be reallocated into unlocked memory.
+pub struct SecretString {
+    inner: SecretBox<String>,
+}
+
+impl SecretString {
+    /// Create a secret string, locking the string's character buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret's memory can't be locked. See [`Self::try_new`] for a
+    /// non-panicking alternative.
+    pub fn new(secret: String) -> Self {
+        Self::try_new(secret).unwrap_or_else(|err| fail(err))
+    }
+
+    /// Create a secret string, returning an error if the secret's memory can't be locked.
+    pub fn try_new(secret: String) -> Result<Self, SecretBoxError> {
+        let inner = SecretBox::try_new_with_region(Box::new(secret))?;
+        Ok(Self { inner })
+    }
+
+    /// Move the environment variable `key`, e.g. `DATABASE_PASSWORD`, into a secret string and
+    /// remove it from the environment.
+    ///
+    /// On Unix the value is also overwritten with zeros in the environment block before the
+    /// variable is removed, so it doesn't linger in memory. That covers
+    /// `/proc/self/environ`, which shows the environment the process was started with. It
+    /// doesn't reach copies made before, e.g. by child processes already spawned, and on
+    /// Windows the value is only removed.
+    ///
+    /// Like [`std::env::remove_var`], call this before spawning threads: no other thread may
+    /// read or modify the environment at the same time, which includes C libraries calling
+    /// `getenv`. If the variable is missing or isn't valid Unicode, the environment is left
+    /// untouched and the error returned, [`VarError::NotUnicode`] holds the value.
+    ///
+    /// ```
+    /// use shush_rs::{ExposeSecret, SecretString};
+    ///
+    /// std::env::set_var("EXAMPLE_DATABASE_PASSWORD", "hunter2");
+    /// let password = SecretString::from_env("EXAMPLE_DATABASE_PASSWORD").unwrap();
+    /// assert_eq!(&*password.expose_secret(), "hunter2");
+    /// assert!(std::env::var_os("EXAMPLE_DATABASE_PASSWORD").is_none());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret's memory can't be locked.
+    pub fn from_env(key: &str) -> Result<Self, VarError> {
+        let value = std::env::var_os(key).ok_or(VarError::NotPresent)?;
+        let secret = Self::new(value.into_string().map_err(VarError::NotUnicode)?);
+        scrub_env_var(key);
+        Ok(secret)
+    }
+
+    /// Lend the string's bytes to `f`, see [`SecretVec::with_bytes`](crate::SecretVec::with_bytes).
+    pub fn with_bytes<R>(&self, f: impl FnOnce(&[u8]) -> R) -> R {
+        self.inner.with_exposed(|secret| f(secret.as_bytes()))
+    }
+
+    /// Turn the string into its bytes, e.g. to hash a password.
+    ///
+    /// The vector reuses the string's buffer, which stays locked, the characters aren't
+    /// copied.
+    ///
+    /// ```
+    /// use shush_rs::{ExposeSecret, SecretString};
+    ///
+    /// let password = SecretString::from(String::from("hunter2"));
+    /// assert_eq!(*password.into_bytes().expose_secret(), b"hunter2");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the vector's memory can't be locked.
+    pub fn into_bytes(self) -> SecretVec<u8> {
+        self.inner.convert_with_region(String::into_bytes)
+    }
+
+    pub(crate) fn from_inner(inner: SecretBox<String>) -> Self {
+        Self { inner }
+    }
+
+    #[cfg(any(test, feature = "secrecy-compat"))]
+    pub(crate) fn as_inner(&self) -> &SecretBox<String> {
+        &self.inner
+    }
+
+    /// Clone the string, returning an error if the clone's memory can't be locked, see
+    /// [`SecretBox::try_clone`].
+    pub fn try_clone(&self) -> Result<Self, SecretBoxError> {
+        let mut secret = Self::try_new(self.inner.expose_secret().clone())?;
+        secret.inner.debug_label = self.inner.debug_label;
+        Ok(secret)
+    }
+
+    /// Number of bytes locked for the string, see [`SecretBox::locked_bytes`].
+    pub fn locked_bytes(&self) -> usize {
+        self.inner.locked_bytes()
+    }
+
+    /// Tag the string with a label printed by its `Debug` impl, see
+    /// [`SecretBox::with_debug_label`].
+    pub fn with_debug_label(mut self, label: &'static str) -> Self {
+        self.inner.debug_label = Some(label);
+        self
+    }
+}
+
+/// Overwrite the value of the environment variable `key` where the platform allows it and
+/// remove the variable.
+fn scrub_env_var(key: &str) {
+    #[cfg(unix)]
+    if let Ok(key) = std::ffi::CString::new(key) {
+        let value = unsafe { libc::getenv(key.as_ptr()) };
+        if !value.is_null() {
+            let len = unsafe { libc::strlen(value) };
+            unsafe { std::slice::from_raw_parts_mut(value.cast::<u8>(), len) }.zeroize();
+        }
+    }
+    std::env::remove_var(key);
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        Self::new(secret)
+    }
+}
+
+impl FromStr for SecretString {
+    type Err = core::convert::Infallible;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(SecretString::new(s.to_string()))
+    }
+}
+
+impl Default for SecretString {
+    fn default() -> Self {
+        Self::new(String::new())
+    }
+}
+
+impl Clone for SecretString {
+    fn clone(&self) -> Self {
+        self.try_clone().unwrap_or_else(|err| fail(err))
+    }
+}
+
+impl Zeroize for SecretString {
+    fn zeroize(&mut self) {
+        self.inner.zeroize()
+    }
+}
+
+impl ZeroizeOnDrop for SecretString {}
+
+impl Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretString(")?;
+        if let Some(label) = self.inner.debug_label {
+            write!(f, "{}: ", label)?;
+        }
+        write!(f, "[REDACTED])")
+    }
+}
+
+impl ExposeSecret<str> for SecretString {
+    fn expose_secret(&self) -> SecretGuard<'_, str> {
+        self.inner.expose_secret().map(String::as_str)
+    }
+
+    fn expose_secret_mut(&mut self) -> SecretGuardMut<'_, str> {
+        self.inner.expose_secret_mut().map(String::as_mut_str)
+    }
+
+    fn try_expose_secret(&self) -> Result<SecretGuard<'_, str>, ExposeError> {
+        Ok(self.inner.try_expose_secret()?.map(String::as_str))
+    }
+
+    fn try_expose_secret_mut(&mut self) -> Result<SecretGuardMut<'_, str>, ExposeError> {
+        Ok(self.inner.try_expose_secret_mut()?.map(String::as_mut_str))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_string_locks_buffer() {
+        let secret = SecretString::new(String::from("Encrypted"));
+        assert!(secret.inner.heap_region.is_some());
+        assert_eq!(&*secret.expose_secret(), "Encrypted");
+        assert_eq!(secret.with_bytes(<[u8]>::len), 9);
+    }
+
+    #[test]
+    fn test_secret_string_into_bytes() {
+        let secret = SecretString::new(String::from("Encrypted")).with_debug_label("password");
+        let buffer = secret.expose_secret().as_ptr();
+        let heap_region = secret.inner.heap_region.map(|region| region.start);
+
+        let bytes = secret.into_bytes();
+        assert_eq!(bytes.expose_secret().as_ptr(), buffer);
+        assert_eq!(bytes.heap_region.map(|region| region.start), heap_region);
+        assert_eq!(bytes.debug_label, Some("password"));
+
+        let secret = bytes.into_string().unwrap();
+        assert_eq!(secret.expose_secret().as_ptr(), buffer);
+        assert_eq!(&*secret.expose_secret(), "Encrypted");
+        assert!(secret.inner.heap_region.is_some());
+    }
+
+    #[test]
+    fn test_secret_string_from_env() {
+        std::env::set_var("SHUSH_TEST_FROM_ENV", "Encrypted");
+        let secret = SecretString::from_env("SHUSH_TEST_FROM_ENV").unwrap();
+        assert_eq!(&*secret.expose_secret(), "Encrypted");
+        assert!(secret.inner.heap_region.is_some());
+        assert!(std::env::var_os("SHUSH_TEST_FROM_ENV").is_none());
+
+        assert_eq!(
+            SecretString::from_env("SHUSH_TEST_FROM_ENV").unwrap_err(),
+            VarError::NotPresent
+        );
+    }
+
+    #[test]
+    fn test_secret_string_from_str() {
+        let mut secret: SecretString = "Encrypted".parse().unwrap();
+        secret.expose_secret_mut().make_ascii_uppercase();
+        assert_eq!(&*secret.expose_secret(), "ENCRYPTED");
+        assert_eq!(format!("{:?}", secret), "SecretString([REDACTED])");
+        assert_eq!(
+            format!("{:?}", secret.with_debug_label("password").clone()),
+            "SecretString(password: [REDACTED])"
+        );
+    }
+}
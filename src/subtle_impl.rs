@@ -0,0 +1,201 @@
+//! Constant-time comparison of secrets using [`subtle`].
+//!
+//! Prefer these over comparing exposed secrets with `==`, which returns as soon as a difference
+//! is found and leaks the position of that difference through timing.
+//!
+//! For the same reason secrets implement neither `Ord` nor `PartialOrd`: ordering bytes with
+//! `Ord` stops at the first differing byte. Byte secrets offer a constant-time `ct_cmp`
+//! instead, e.g. to keep them in a sorted structure.
+
+use std::cmp::Ordering;
+
+use subtle::{
+    Choice, ConditionallySelectable, ConstantTimeEq, ConstantTimeGreater, ConstantTimeLess,
+};
+use zeroize::Zeroize;
+
+use crate::{ExposeSecret, SecretArray, SecretBox, SecretString, SecretVec};
+
+/// Order `a` and `b` lexicographically, looking at every byte of the shorter one no matter
+/// where they differ.
+fn ct_cmp_bytes(a: &[u8], b: &[u8]) -> Ordering {
+    let mut result = 0i8;
+    for (x, y) in a.iter().zip(b) {
+        let mut here = i8::conditional_select(&0, &-1, x.ct_lt(y));
+        here.conditional_assign(&1, x.ct_gt(y));
+        // Keep the first difference, later bytes must not change the result
+        result.conditional_assign(&here, result.ct_eq(&0));
+    }
+    match result {
+        0 => a.len().cmp(&b.len()),
+        result => result.cmp(&0),
+    }
+}
+
+impl<S: Zeroize + ConstantTimeEq + ?Sized> ConstantTimeEq for SecretBox<S> {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        (*self.expose_secret()).ct_eq(&*other.expose_secret())
+    }
+}
+
+impl<S: Zeroize + ConditionallySelectable> SecretBox<S> {
+    /// Select `a` if `choice` is 0 or `b` if it's 1 into a new secret, without branching on
+    /// `choice`.
+    ///
+    /// The new secret starts as a copy of `a` and `b` is conditionally assigned to it in
+    /// place, so the selected value only ever exists in locked memory.
+    ///
+    /// ```
+    /// use shush_rs::{subtle::Choice, ExposeSecret, SecretBox};
+    ///
+    /// let a = SecretBox::new(Box::new([1u8; 32]));
+    /// let b = SecretBox::new(Box::new([2u8; 32]));
+    /// let selected = SecretBox::conditional_select(&a, &b, Choice::from(1));
+    /// assert_eq!(*selected.expose_secret(), [2; 32]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new secret's memory can't be locked.
+    pub fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        let mut selected = SecretBox::new_with_ctr(|| *a.expose_secret());
+        selected
+            .expose_secret_mut()
+            .conditional_assign(&b.expose_secret(), choice);
+        selected
+    }
+}
+
+impl SecretVec<u8> {
+    /// Compare two secret byte vectors in constant time.
+    ///
+    /// Only the contents are compared in constant time, the lengths are not secret.
+    pub fn ct_eq(&self, other: &Self) -> Choice {
+        self.expose_secret()
+            .as_slice()
+            .ct_eq(other.expose_secret().as_slice())
+    }
+
+    /// Order two secret byte vectors lexicographically in constant time.
+    ///
+    /// Like [`Self::ct_eq`] the lengths are not secret, a prefix orders before the longer
+    /// vector. `SecretVec` deliberately doesn't implement `Ord`, which would leak the position
+    /// of the first difference.
+    ///
+    /// ```
+    /// use shush_rs::SecretVec;
+    /// use std::cmp::Ordering;
+    ///
+    /// let a = SecretVec::from(vec![1u8, 2, 3]);
+    /// let b = SecretVec::from(vec![1u8, 3, 0]);
+    /// assert_eq!(a.ct_cmp(&b), Ordering::Less);
+    /// ```
+    ///
+    /// ```compile_fail
+    /// use shush_rs::SecretVec;
+    ///
+    /// let a = SecretVec::from(vec![1u8, 2, 3]);
+    /// let b = SecretVec::from(vec![1u8, 3, 0]);
+    /// let _ = a < b;
+    /// ```
+    pub fn ct_cmp(&self, other: &Self) -> Ordering {
+        ct_cmp_bytes(
+            self.expose_secret().as_slice(),
+            other.expose_secret().as_slice(),
+        )
+    }
+}
+
+impl<const N: usize> SecretArray<u8, N> {
+    /// Order two secret byte arrays lexicographically in constant time, see
+    /// [`SecretVec::ct_cmp`].
+    pub fn ct_cmp(&self, other: &Self) -> Ordering {
+        ct_cmp_bytes(&*self.expose_secret(), &*other.expose_secret())
+    }
+}
+
+impl ConstantTimeEq for SecretString {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.expose_secret()
+            .as_bytes()
+            .ct_eq(other.expose_secret().as_bytes())
+    }
+}
+
+impl SecretString {
+    /// Order two secret strings by their bytes in constant time, see [`SecretVec::ct_cmp`].
+    pub fn ct_cmp(&self, other: &Self) -> Ordering {
+        ct_cmp_bytes(
+            self.expose_secret().as_bytes(),
+            other.expose_secret().as_bytes(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_box_ct_eq() {
+        let a = SecretBox::new(Box::new(42u64));
+        let b = SecretBox::new(Box::new(42u64));
+        let c = SecretBox::new(Box::new(7u64));
+
+        assert!(bool::from(a.ct_eq(&b)));
+        assert!(!bool::from(a.ct_eq(&c)));
+    }
+
+    #[test]
+    fn test_secret_vec_and_string_ct_eq() {
+        assert!(bool::from(
+            SecretVec::from(vec![1u8, 2, 3]).ct_eq(&SecretVec::from(vec![1u8, 2, 3]))
+        ));
+        assert!(!bool::from(
+            SecretVec::from(vec![1u8, 2, 3]).ct_eq(&SecretVec::from(vec![1u8, 2, 4]))
+        ));
+
+        let token = SecretString::from(String::from("token"));
+        assert!(bool::from(token.ct_eq(&"token".parse().unwrap())));
+        assert!(!bool::from(token.ct_eq(&"tokem".parse().unwrap())));
+    }
+
+    #[test]
+    fn test_secret_box_conditional_select() {
+        let a = SecretBox::new(Box::new(1u64));
+        let b = SecretBox::new(Box::new(2u64));
+        assert_eq!(
+            *SecretBox::conditional_select(&a, &b, Choice::from(0)).expose_secret(),
+            1
+        );
+        assert_eq!(
+            *SecretBox::conditional_select(&a, &b, Choice::from(1)).expose_secret(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_ct_cmp() {
+        let cases: [(&[u8], &[u8]); 6] = [
+            (b"", b""),
+            (b"abc", b"abc"),
+            (b"abc", b"abd"),
+            (b"abd", b"abc"),
+            (b"ab", b"abc"),
+            (b"\x00\xff", b"\xff\x00"),
+        ];
+        for (a, b) in cases {
+            assert_eq!(
+                SecretVec::from(a.to_vec()).ct_cmp(&SecretVec::from(b.to_vec())),
+                a.cmp(b)
+            );
+        }
+
+        let token = SecretString::from(String::from("token"));
+        assert_eq!(token.ct_cmp(&"tokem".parse().unwrap()), Ordering::Greater);
+        assert_eq!(
+            SecretArray::new(Box::new([1u8, 2])).ct_cmp(&SecretArray::new(Box::new([2u8, 1]))),
+            Ordering::Less
+        );
+    }
+}